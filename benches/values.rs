@@ -0,0 +1,67 @@
+//! Times the virtual machine on a few programs, to compare the representations of values:
+//!
+//! ```sh
+//! cargo bench --bench values
+//! cargo bench --bench values --features nan-boxing
+//! ```
+
+use std::{
+    io,
+    time::{Duration, Instant},
+};
+
+use interpreter_starter_rust::Vm;
+
+const RUNS: usize = 5;
+
+const PROGRAMS: [(&str, &str); 3] = [
+    (
+        "fib",
+        "fun fib(n) { if (n < 2) return n; return fib(n - 2) + fib(n - 1); }\n\
+         print fib(27);\n",
+    ),
+    (
+        "loops",
+        "var sum = 0;\n\
+         for (var i = 0; i < 2000000; i = i + 1) {\n\
+             if (i / 2 > 10 and !(i == 7)) sum = sum + i; else sum = sum - 1;\n\
+         }\n\
+         print sum;\n",
+    ),
+    (
+        "methods",
+        "class Counter {\n\
+             init() { this.count = 0; }\n\
+             add(n) { this.count = this.count + n; return this; }\n\
+         }\n\
+         var counter = Counter();\n\
+         for (var i = 0; i < 500000; i = i + 1) counter.add(1).add(2);\n\
+         print counter.count;\n",
+    ),
+];
+
+/// The fastest of several runs of `input`, compiling included.
+fn time(input: &str) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            let mut vm = Vm::new(io::sink());
+            let function = vm.compile(input).expect("benchmarks compile");
+            vm.interpret(function).expect("benchmarks run");
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+fn main() {
+    let representation = if cfg!(feature = "nan-boxing") {
+        "nan-boxed"
+    } else {
+        "enum"
+    };
+    println!("values: {representation}, best of {RUNS}");
+    for (name, input) in PROGRAMS {
+        println!("{name:<8} {:>10.2?}", time(input));
+    }
+}
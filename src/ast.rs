@@ -0,0 +1,173 @@
+use std::{fmt::Display, rc::Rc};
+
+use crate::{Interner, Literal, Symbol, Token};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Expr<'a> {
+    Nil,
+    Bool(bool),
+    Literal(Literal<'a>),
+    Grouping(Box<Self>),
+    Unary {
+        op: Token<'a>,
+        right: Box<Self>,
+    },
+    Binary {
+        left: Box<Self>,
+        op: Token<'a>,
+        right: Box<Self>,
+    },
+    Logical {
+        left: Box<Self>,
+        op: Token<'a>,
+        right: Box<Self>,
+    },
+    Variable {
+        /// Interned by the [`Parser`](crate::Parser), as every name in the tree is.
+        name: Symbol,
+        line: usize,
+        /// Scopes between this use and the declaration, filled in by the resolver.
+        /// `None` means a global.
+        depth: Option<usize>,
+    },
+    Assign {
+        name: Symbol,
+        line: usize,
+        depth: Option<usize>,
+        value: Box<Self>,
+    },
+    Call {
+        callee: Box<Self>,
+        line: usize,
+        arguments: Vec<Self>,
+    },
+    Get {
+        object: Box<Self>,
+        name: Symbol,
+        line: usize,
+    },
+    Set {
+        object: Box<Self>,
+        name: Symbol,
+        line: usize,
+        value: Box<Self>,
+    },
+    This {
+        line: usize,
+        depth: Option<usize>,
+    },
+    Super {
+        line: usize,
+        depth: Option<usize>,
+        method: Symbol,
+    },
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Stmt<'a> {
+    Expression(Expr<'a>),
+    Print(Expr<'a>),
+    Var {
+        name: Symbol,
+        line: usize,
+        initializer: Option<Expr<'a>>,
+    },
+    Block(Vec<Self>),
+    If {
+        condition: Expr<'a>,
+        then_branch: Box<Self>,
+        else_branch: Option<Box<Self>>,
+    },
+    While {
+        condition: Expr<'a>,
+        body: Box<Self>,
+    },
+    Function(Rc<Function<'a>>),
+    Class(Class<'a>),
+    Return {
+        line: usize,
+        value: Option<Expr<'a>>,
+    },
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Function<'a> {
+    pub(crate) name: Symbol,
+    pub(crate) line: usize,
+    pub(crate) params: Vec<(Symbol, usize)>,
+    pub(crate) body: Vec<Stmt<'a>>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Class<'a> {
+    pub(crate) name: Symbol,
+    pub(crate) line: usize,
+    /// Always an [`Expr::Variable`].
+    pub(crate) superclass: Option<Expr<'a>>,
+    pub(crate) methods: Vec<Rc<Function<'a>>>,
+}
+
+impl<'a> Expr<'a> {
+    /// Shows this expression in prefix notation, spelling out names as `names` interned them.
+    #[must_use]
+    pub const fn display<'e>(&'e self, names: &'e Interner) -> Printed<'e, 'a> {
+        Printed { expr: self, names }
+    }
+}
+
+/// An [`Expr`] along with the [`Interner`] needed to print its names.
+pub struct Printed<'e, 'a> {
+    expr: &'e Expr<'a>,
+    names: &'e Interner,
+}
+
+impl Display for Printed<'_, '_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names = self.names;
+        let name = |symbol| names.resolve(symbol);
+        match self.expr {
+            Expr::Nil => write!(f, "nil"),
+            Expr::Bool(x) => x.fmt(f),
+            Expr::Literal(x) => x.fmt(f),
+            Expr::Grouping(x) => write!(f, "(group {})", x.display(names)),
+            Expr::Unary { op, right, .. } => write!(f, "({} {})", op.lexeme, right.display(names)),
+            Expr::Binary { left, op, right } | Expr::Logical { left, op, right } => write!(
+                f,
+                "({} {} {})",
+                op.lexeme,
+                left.display(names),
+                right.display(names)
+            ),
+            Expr::Variable { name: x, .. } => name(*x).fmt(f),
+            Expr::Assign { name: x, value, .. } => {
+                write!(f, "(= {} {})", name(*x), value.display(names))
+            }
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                write!(f, "(call {}", callee.display(names))?;
+                for argument in arguments {
+                    write!(f, " {}", argument.display(names))?;
+                }
+                write!(f, ")")
+            }
+            Expr::Get {
+                object, name: x, ..
+            } => write!(f, "(. {} {})", object.display(names), name(*x)),
+            Expr::Set {
+                object,
+                name: x,
+                value,
+                ..
+            } => write!(
+                f,
+                "(= (. {} {}) {})",
+                object.display(names),
+                name(*x),
+                value.display(names)
+            ),
+            Expr::This { .. } => write!(f, "this"),
+            Expr::Super { method, .. } => write!(f, "(. super {})", name(*method)),
+        }
+    }
+}
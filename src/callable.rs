@@ -0,0 +1,96 @@
+use std::{
+    cell::RefCell,
+    fmt::{Debug, Display},
+    rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{ast, class::Instance, environment::Environment, Symbol, Value};
+
+/// A user-defined function together with the environment it was declared in.
+pub struct Closure<'a> {
+    /// The declaration's name, spelled out for printing.
+    pub(crate) name: Rc<str>,
+    pub(crate) declaration: Rc<ast::Function<'a>>,
+    pub(crate) environment: Rc<RefCell<Environment<'a>>>,
+    /// Initializers always return `this`.
+    pub(crate) is_initializer: bool,
+}
+
+impl<'a> Closure<'a> {
+    #[must_use]
+    pub(crate) const fn new(
+        name: Rc<str>,
+        declaration: Rc<ast::Function<'a>>,
+        environment: Rc<RefCell<Environment<'a>>>,
+        is_initializer: bool,
+    ) -> Self {
+        Self {
+            name,
+            declaration,
+            environment,
+            is_initializer,
+        }
+    }
+
+    /// Turns a method into a function whose `this`, interned as `this`, refers to `instance`.
+    #[must_use]
+    pub(crate) fn bind(&self, this: Symbol, instance: Rc<Instance<'a>>) -> Self {
+        let mut environment = Environment::new(Rc::clone(&self.environment));
+        environment.define(this, Value::Instance(instance));
+        Self::new(
+            Rc::clone(&self.name),
+            Rc::clone(&self.declaration),
+            Rc::new(RefCell::new(environment)),
+            self.is_initializer,
+        )
+    }
+
+    #[inline]
+    pub(crate) fn arity(&self) -> usize {
+        self.declaration.params.len()
+    }
+}
+
+impl Debug for Closure<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Closure")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Display for Closure<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<fn {}>", self.name)
+    }
+}
+
+/// A function implemented by the interpreter itself.
+#[derive(Debug, Clone, Copy)]
+pub struct Native {
+    pub(crate) name: &'static str,
+    pub(crate) arity: usize,
+    pub(crate) fun: for<'a> fn(&[Value<'a>]) -> Value<'a>,
+}
+
+impl Native {
+    pub const CLOCK: Self = Self {
+        name: "clock",
+        arity: 0,
+        fun: |_| {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            Value::Number(now.as_secs_f64())
+        },
+    };
+
+    pub const ALL: [Self; 1] = [Self::CLOCK];
+}
+
+impl Display for Native {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<native fn>")
+    }
+}
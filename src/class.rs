@@ -0,0 +1,69 @@
+use std::{cell::RefCell, collections::HashMap, fmt::Display, rc::Rc};
+
+use crate::{callable::Closure, Symbol, Value};
+
+#[derive(Debug)]
+pub struct Class<'a> {
+    pub(crate) name: Rc<str>,
+    pub(crate) superclass: Option<Rc<Self>>,
+    pub(crate) methods: HashMap<Symbol, Rc<Closure<'a>>>,
+}
+
+impl<'a> Class<'a> {
+    /// Looks `name` up in this class, then in its superclasses.
+    pub(crate) fn find_method(&self, name: Symbol) -> Option<&Rc<Closure<'a>>> {
+        self.methods.get(&name).or_else(|| {
+            self.superclass
+                .as_ref()
+                .and_then(|superclass| superclass.find_method(name))
+        })
+    }
+
+    /// Arity of the initializer, interned as `init`.
+    #[inline]
+    pub(crate) fn arity(&self, init: Symbol) -> usize {
+        self.find_method(init).map_or(0, |init| init.arity())
+    }
+}
+
+impl Display for Class<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.name.fmt(f)
+    }
+}
+
+#[derive(Debug)]
+pub struct Instance<'a> {
+    pub(crate) class: Rc<Class<'a>>,
+    fields: RefCell<HashMap<Symbol, Value<'a>>>,
+}
+
+impl<'a> Instance<'a> {
+    #[must_use]
+    pub(crate) fn new(class: Rc<Class<'a>>) -> Self {
+        Self {
+            class,
+            fields: RefCell::default(),
+        }
+    }
+
+    /// Fields shadow methods, which get bound to `this`, interned as `symbol`, on access.
+    pub(crate) fn get(this: &Rc<Self>, name: Symbol, symbol: Symbol) -> Option<Value<'a>> {
+        if let Some(value) = this.fields.borrow().get(&name) {
+            return Some(value.clone());
+        }
+        this.class
+            .find_method(name)
+            .map(|method| Value::Closure(Rc::new(method.bind(symbol, Rc::clone(this)))))
+    }
+
+    pub(crate) fn set(&self, name: Symbol, value: Value<'a>) {
+        self.fields.borrow_mut().insert(name, value);
+    }
+}
+
+impl Display for Instance<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} instance", self.class)
+    }
+}
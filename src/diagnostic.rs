@@ -0,0 +1,246 @@
+use std::{
+    fmt::Display,
+    io::{self, Write},
+};
+
+use crate::Span;
+
+/// An error that can be reported against the source it came from.
+pub trait Diagnostic: Display {
+    /// Line the error was reported on.
+    fn line(&self) -> usize;
+
+    /// Exact location in the source, when the error knows it.
+    fn span(&self) -> Option<Span> {
+        None
+    }
+
+    /// The error message, without the `[line N] Error` prefix.
+    fn message(&self) -> String;
+
+    /// Stable code identifying the kind of error, with an [`Explanation`](crate::Explanation).
+    fn code(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// A hint on how to fix the error.
+    fn help(&self) -> Option<&'static str> {
+        None
+    }
+}
+
+/// How diagnostics are written out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Style {
+    /// `[line N] Error: message`, one line per error.
+    #[default]
+    Plain,
+    /// File and column, the offending source line and an underline below it.
+    Rich,
+}
+
+/// Writes diagnostics for a single source file.
+#[derive(Debug, Clone, Copy)]
+pub struct Renderer<'a> {
+    style: Style,
+    file: &'a str,
+    source: &'a str,
+}
+
+impl<'a> Renderer<'a> {
+    #[must_use]
+    pub const fn new(source: &'a str) -> Self {
+        Self {
+            style: Style::Plain,
+            file: "<input>",
+            source,
+        }
+    }
+
+    /// Renders in `style`, naming `file` as the origin of the source.
+    #[must_use]
+    pub const fn with_style(self, style: Style, file: &'a str) -> Self {
+        Self {
+            style,
+            file,
+            ..self
+        }
+    }
+
+    pub fn render<W, D>(&self, w: &mut W, diagnostic: &D) -> io::Result<()>
+    where
+        W: Write,
+        D: Diagnostic + ?Sized,
+    {
+        match self.style {
+            Style::Plain => writeln!(w, "{diagnostic}"),
+            Style::Rich => self.rich(w, diagnostic),
+        }
+    }
+
+    fn rich<W, D>(&self, w: &mut W, diagnostic: &D) -> io::Result<()>
+    where
+        W: Write,
+        D: Diagnostic + ?Sized,
+    {
+        match diagnostic.code() {
+            Some(code) => writeln!(w, "error[{code}]: {}", diagnostic.message())?,
+            None => writeln!(w, "error: {}", diagnostic.message())?,
+        }
+        let line = diagnostic.line();
+        let snippet = match diagnostic.span() {
+            Some(span) => {
+                writeln!(w, " --> {}:{line}:{}", self.file, span.column)?;
+                Some(self.underlined(span))
+            }
+            None if line > 0 => {
+                writeln!(w, " --> {}:{line}", self.file)?;
+                self.source
+                    .lines()
+                    .nth(line - 1)
+                    .map(|x| (x, String::new()))
+            }
+            None => None,
+        };
+
+        let gutter = " ".repeat(line.to_string().len());
+        if let Some((source, underline)) = snippet {
+            writeln!(w, "{gutter} |")?;
+            writeln!(w, "{line} | {source}")?;
+            if underline.is_empty() {
+                writeln!(w, "{gutter} |")?;
+            } else {
+                writeln!(w, "{gutter} | {underline}")?;
+            }
+        }
+        if let Some(help) = diagnostic.help() {
+            writeln!(w, "{gutter} = help: {help}")?;
+        }
+        if let Some(code) = diagnostic.code() {
+            writeln!(
+                w,
+                "{gutter} = note: run `explain {code}` for more about this error"
+            )?;
+        }
+        Ok(())
+    }
+
+    /// The line containing the start of `span`, and a `^~~` underline of the part of `span`
+    /// that lies on it.
+    fn underlined(&self, span: Span) -> (&'a str, String) {
+        let start = self.source[..span.start].rfind('\n').map_or(0, |i| i + 1);
+        let end = self.source[span.start..]
+            .find('\n')
+            .map_or(self.source.len(), |i| span.start + i);
+        let source = &self.source[start..end];
+
+        let width = self.source[span.start..span.end.min(end)].chars().count();
+        let underline = format!(
+            "{}^{}",
+            " ".repeat(span.column - 1),
+            "~".repeat(width.saturating_sub(1))
+        );
+        (source.trim_end_matches('\r'), underline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Parser, Resolver, Scanner};
+
+    use pretty_assertions::assert_eq;
+
+    fn render(input: &str, style: Style) -> String {
+        let mut buf = Vec::new();
+        let renderer = Renderer::new(input).with_style(style, "test.lox");
+        let mut parser = Parser::new(input);
+        match parser.parse() {
+            Ok(mut statements) => {
+                let mut names = parser.into_names();
+                for e in Resolver::new(&mut names)
+                    .resolve(&mut statements)
+                    .unwrap_err()
+                {
+                    renderer.render(&mut buf, &e).unwrap();
+                }
+            }
+            Err(errors) => {
+                for e in errors {
+                    renderer.render(&mut buf, &e).unwrap();
+                }
+            }
+        }
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn plain() {
+        assert_eq!(
+            render("print 1;\n\"foo", Style::Plain),
+            "[line 2] Error: Unterminated string.\n"
+        );
+    }
+
+    #[test]
+    fn lexical() {
+        let input = "print 1;\nprint \"foo;\n";
+        let e = Scanner::new(input).find_map(Result::err).unwrap();
+        let mut buf = Vec::new();
+        Renderer::new(input)
+            .with_style(Style::Rich, "test.lox")
+            .render(&mut buf, &e)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "\
+error[L0002]: Unterminated string.
+ --> test.lox:2:7
+  |
+2 | print \"foo;
+  |       ^~~~~
+  = help: strings must be closed with `\"`
+  = note: run `explain L0002` for more about this error
+"
+        );
+    }
+
+    #[test]
+    fn syntax() {
+        assert_eq!(
+            render("var x = 1 +;", Style::Rich),
+            "\
+error[P0001]: Expect expression.
+ --> test.lox:1:12
+  |
+1 | var x = 1 +;
+  |            ^
+  = note: run `explain P0001` for more about this error
+"
+        );
+        assert_eq!(
+            render("print clock\n", Style::Rich),
+            "error[P0002]: Expect ';' after value.\n \
+             --> test.lox:2:1\n  \
+             |\n\
+             2 | \n  \
+             | ^\n  \
+             = note: run `explain P0002` for more about this error\n"
+        );
+    }
+
+    #[test]
+    fn semantic() {
+        assert_eq!(
+            render("{\n  var a = a;\n}", Style::Rich),
+            "\
+error[S0001]: Can't read local variable in its own initializer.
+ --> test.lox:2
+  |
+2 |   var a = a;
+  |
+  = note: run `explain S0001` for more about this error
+"
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use std::io::{self, Write};
+
+use crate::{vm, Diagnostic, Renderer, Style, Vm};
+
+/// Prints the bytecode a program compiles to.
+pub struct Disassembler<'a, O, E> {
+    input: &'a str,
+    out: &'a mut O,
+    err: &'a mut E,
+    diagnostics: Renderer<'a>,
+    pub code: i32,
+}
+
+impl<'a, O, E> Disassembler<'a, O, E>
+where
+    O: Write,
+    E: Write,
+{
+    pub fn new(input: &'a str, out: &'a mut O, err: &'a mut E) -> Self {
+        Self {
+            input,
+            out,
+            err,
+            diagnostics: Renderer::new(input),
+            code: 0,
+        }
+    }
+
+    /// Reports errors in `style`, naming `file` as the origin of the input.
+    #[must_use]
+    pub const fn with_diagnostics(mut self, style: Style, file: &'a str) -> Self {
+        self.diagnostics = self.diagnostics.with_style(style, file);
+        self
+    }
+
+    pub fn disassemble(&mut self) -> io::Result<()> {
+        let mut vm = Vm::new(io::sink());
+        match vm.compile(self.input) {
+            Ok(function) => vm.disassemble(function, self.out)?,
+            Err(vm::Errors::Syntax(errors)) => self.compile_errors(errors)?,
+            Err(vm::Errors::Semantic(errors)) => self.compile_errors(errors)?,
+        }
+
+        self.out.flush()?;
+        self.err.flush()?;
+        Ok(())
+    }
+
+    fn compile_errors<T: Diagnostic>(&mut self, errors: Vec<T>) -> io::Result<()> {
+        self.code = 65;
+        for e in errors {
+            self.diagnostics.render(self.err, &e)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    fn disassemble(input: &str) -> (String, String, i32) {
+        let mut out_buf = Vec::new();
+        let mut err_buf = Vec::new();
+        let mut disassembler = Disassembler::new(input, &mut out_buf, &mut err_buf);
+        disassembler.disassemble().unwrap();
+        let code = disassembler.code;
+        (
+            String::from_utf8(out_buf).unwrap(),
+            String::from_utf8(err_buf).unwrap(),
+            code,
+        )
+    }
+
+    #[test]
+    fn out() {
+        let input = "\
+        fun add(a) {\n\
+            var b = 1;\n\
+            fun f() { return a + b; }\n\
+            return f;\n\
+        }\n\
+        for (var i = 0; i < 2; i = i + 1)\n\
+            print add(i)();\n\
+        ";
+        assert_eq!(
+            disassemble(input),
+            (
+                "\
+                == <script> ==\n\
+                constants:\n   \
+                   0 'add'\n   \
+                   1 <fn add>\n   \
+                   2 '0'\n   \
+                   3 '2'\n   \
+                   4 '1'\n   \
+                   5 'add'\n\
+                0000    5 OP_CLOSURE          1 <fn add>\n\
+                0003    | OP_DEFINE_GLOBAL    0 'add'\n\
+                0006    6 OP_CONSTANT         2 '0'\n\
+                0009    | OP_GET_LOCAL        1\n\
+                0011    | OP_CONSTANT         3 '2'\n\
+                0014    | OP_LESS\n\
+                0015    | OP_JUMP_IF_FALSE   15 -> 47\n\
+                0018    | OP_POP\n\
+                0019    | OP_JUMP            19 -> 34\n\
+                0022    | OP_GET_LOCAL        1\n\
+                0024    | OP_CONSTANT         4 '1'\n\
+                0027    | OP_ADD\n\
+                0028    | OP_SET_LOCAL        1\n\
+                0030    | OP_POP\n\
+                0031    | OP_LOOP            31 -> 9\n\
+                0034    7 OP_GET_GLOBAL       5 'add'\n\
+                0037    | OP_GET_LOCAL        1\n\
+                0039    | OP_CALL             1\n\
+                0041    | OP_CALL             0\n\
+                0043    | OP_PRINT\n\
+                0044    | OP_LOOP            44 -> 22\n\
+                0047    6 OP_POP\n\
+                0048    7 OP_POP\n\
+                0049    | OP_NIL\n\
+                0050    | OP_RETURN\n\
+                == <fn add> ==\n\
+                constants:\n   \
+                   0 '1'\n   \
+                   1 <fn f>\n\
+                0000    2 OP_CONSTANT         0 '1'\n\
+                0003    3 OP_CLOSURE          1 <fn f>\n\
+                0006    |                     local 1\n\
+                0008    |                     local 2\n\
+                0010    4 OP_GET_LOCAL        3\n\
+                0012    | OP_RETURN\n\
+                0013    5 OP_NIL\n\
+                0014    | OP_RETURN\n\
+                == <fn f> ==\n\
+                0000    3 OP_GET_UPVALUE      0\n\
+                0002    | OP_GET_UPVALUE      1\n\
+                0004    | OP_ADD\n\
+                0005    | OP_RETURN\n\
+                0006    | OP_NIL\n\
+                0007    | OP_RETURN\n\
+                "
+                .into(),
+                String::new(),
+                0
+            )
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            disassemble("print;"),
+            (
+                String::new(),
+                "[line 1] Error at ';': Expect expression.\n".into(),
+                65
+            )
+        );
+    }
+}
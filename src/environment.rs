@@ -0,0 +1,111 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::{Symbol, Value};
+
+/// Variables of a scope, keyed by the symbols of their names in the
+/// [`Interpreter`](crate::Interpreter)'s interner.
+#[derive(Debug, Default)]
+pub struct Environment<'a> {
+    values: HashMap<Symbol, Value<'a>>,
+    enclosing: Option<Rc<RefCell<Self>>>,
+}
+
+impl<'a> Environment<'a> {
+    #[must_use]
+    pub(crate) fn new(enclosing: Rc<RefCell<Self>>) -> Self {
+        Self {
+            values: HashMap::new(),
+            enclosing: Some(enclosing),
+        }
+    }
+
+    pub(crate) fn define(&mut self, name: Symbol, value: Value<'a>) {
+        self.values.insert(name, value);
+    }
+
+    pub(crate) fn get(&self, name: Symbol) -> Option<Value<'a>> {
+        if let Some(value) = self.values.get(&name) {
+            return Some(value.clone());
+        }
+        self.enclosing
+            .as_ref()
+            .and_then(|enclosing| enclosing.borrow().get(name))
+    }
+
+    /// Looks `name` up exactly `distance` scopes away, as computed by the resolver.
+    pub(crate) fn get_at(&self, distance: usize, name: Symbol) -> Option<Value<'a>> {
+        match (distance, &self.enclosing) {
+            (0, _) | (_, None) => self.values.get(&name).cloned(),
+            (_, Some(enclosing)) => enclosing.borrow().get_at(distance - 1, name),
+        }
+    }
+
+    /// Returns whether `name` was defined.
+    pub(crate) fn assign_at(&mut self, distance: usize, name: Symbol, value: Value<'a>) -> bool {
+        match (distance, &self.enclosing) {
+            (0, _) | (_, None) => self
+                .values
+                .get_mut(&name)
+                .map(|slot| *slot = value)
+                .is_some(),
+            (_, Some(enclosing)) => enclosing.borrow_mut().assign_at(distance - 1, name, value),
+        }
+    }
+
+    /// Returns whether `name` was defined.
+    pub(crate) fn assign(&mut self, name: Symbol, value: Value<'a>) -> bool {
+        if let Some(slot) = self.values.get_mut(&name) {
+            *slot = value;
+            return true;
+        }
+        self.enclosing
+            .as_ref()
+            .is_some_and(|enclosing| enclosing.borrow_mut().assign(name, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    use crate::Interner;
+
+    #[test]
+    fn scopes() {
+        let mut names = Interner::new();
+        let [a, b, c] = ["a", "b", "c"].map(|x| names.intern(x));
+        let globals = Rc::new(RefCell::new(Environment::default()));
+        globals.borrow_mut().define(a, Value::Number(1.0));
+        globals.borrow_mut().define(b, Value::Number(2.0));
+
+        let mut local = Environment::new(Rc::clone(&globals));
+        local.define(a, Value::Bool(true));
+        assert!(local.assign(b, Value::Nil));
+
+        assert_eq!(local.get(a), Some(Value::Bool(true)));
+        assert_eq!(local.get(b), Some(Value::Nil));
+        assert_eq!(globals.borrow().get(a), Some(Value::Number(1.0)));
+        assert_eq!(globals.borrow().get(b), Some(Value::Nil));
+        assert_eq!(local.get(c), None);
+        assert!(!local.assign(c, Value::Nil));
+    }
+
+    #[test]
+    fn distance() {
+        let a = Interner::new().intern("a");
+        let globals = Rc::new(RefCell::new(Environment::default()));
+        globals.borrow_mut().define(a, Value::Number(1.0));
+        let outer = Rc::new(RefCell::new(Environment::new(Rc::clone(&globals))));
+        outer.borrow_mut().define(a, Value::Number(2.0));
+        let mut inner = Environment::new(Rc::clone(&outer));
+
+        assert_eq!(inner.get_at(1, a), Some(Value::Number(2.0)));
+        assert_eq!(inner.get_at(2, a), Some(Value::Number(1.0)));
+        assert_eq!(inner.get_at(0, a), None);
+
+        assert!(inner.assign_at(2, a, Value::Nil));
+        assert_eq!(globals.borrow().get(a), Some(Value::Nil));
+        assert_eq!(outer.borrow().get(a), Some(Value::Number(2.0)));
+    }
+}
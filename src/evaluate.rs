@@ -0,0 +1,109 @@
+use std::io::{self, Write};
+
+use crate::{interpreter, Interpreter, Parser, Renderer, Style};
+
+pub struct Evaluator<'a, O, E> {
+    input: &'a str,
+    out: &'a mut O,
+    err: &'a mut E,
+    diagnostics: Renderer<'a>,
+    pub code: i32,
+}
+
+impl<'a, O, E> Evaluator<'a, O, E>
+where
+    O: Write,
+    E: Write,
+{
+    pub fn new(input: &'a str, out: &'a mut O, err: &'a mut E) -> Self {
+        Self {
+            input,
+            out,
+            err,
+            diagnostics: Renderer::new(input),
+            code: 0,
+        }
+    }
+
+    /// Reports errors in `style`, naming `file` as the origin of the input.
+    #[must_use]
+    pub const fn with_diagnostics(mut self, style: Style, file: &'a str) -> Self {
+        self.diagnostics = self.diagnostics.with_style(style, file);
+        self
+    }
+
+    pub fn evaluate(&mut self) -> io::Result<()> {
+        let mut parser = Parser::new(self.input);
+        match parser.parse_expression() {
+            Ok(expr) => match Interpreter::new(io::sink(), parser.into_names()).evaluate(&expr) {
+                Ok(value) => writeln!(self.out, "{value}")?,
+                Err(interpreter::Error::Io(e)) => return Err(e),
+                Err(e) => {
+                    self.code = 70;
+                    self.diagnostics.render(self.err, &e)?;
+                }
+            },
+            Err(errors) => {
+                self.code = 65;
+                for e in errors {
+                    self.diagnostics.render(self.err, &e)?;
+                }
+            }
+        }
+
+        self.out.flush()?;
+        self.err.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    fn evaluate(input: &str) -> (String, String, i32) {
+        let mut out_buf = Vec::new();
+        let mut err_buf = Vec::new();
+        let mut evaluator = Evaluator::new(input, &mut out_buf, &mut err_buf);
+        evaluator.evaluate().unwrap();
+        let code = evaluator.code;
+        (
+            String::from_utf8(out_buf).unwrap(),
+            String::from_utf8(err_buf).unwrap(),
+            code,
+        )
+    }
+
+    #[test]
+    fn out() {
+        assert_eq!(evaluate("(1 + 2) * 3"), ("9\n".into(), String::new(), 0));
+        assert_eq!(evaluate("7 / 2"), ("3.5\n".into(), String::new(), 0));
+        assert_eq!(
+            evaluate("\"foo\" + \"bar\""),
+            ("foobar\n".into(), String::new(), 0)
+        );
+        assert_eq!(evaluate("!nil"), ("true\n".into(), String::new(), 0));
+    }
+
+    #[test]
+    fn err() {
+        assert_eq!(
+            evaluate("-\"foo\""),
+            (
+                String::new(),
+                "Operand must be a number.\n[line 1]\n".into(),
+                70
+            )
+        );
+        assert_eq!(
+            evaluate("(1 +"),
+            (
+                String::new(),
+                "[line 1] Error at end: Expect expression.\n".into(),
+                65
+            )
+        );
+    }
+}
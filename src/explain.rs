@@ -0,0 +1,402 @@
+use std::fmt::Display;
+
+/// The long-form description of an error code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Explanation {
+    pub code: &'static str,
+    pub title: &'static str,
+    pub description: &'static str,
+    /// A program making the mistake.
+    pub example: &'static str,
+    /// The same program, fixed.
+    pub fix: &'static str,
+}
+
+impl Explanation {
+    /// The explanation of `code`, in any case.
+    #[must_use]
+    pub fn find(code: &str) -> Option<&'static Self> {
+        EXPLANATIONS
+            .iter()
+            .find(|x| x.code.eq_ignore_ascii_case(code))
+    }
+
+    #[must_use]
+    pub fn all() -> &'static [Self] {
+        EXPLANATIONS
+    }
+}
+
+impl Display for Explanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}: {}", self.code, self.title)?;
+        writeln!(f)?;
+        writeln!(f, "{}", self.description)?;
+        writeln!(f)?;
+        writeln!(f, "Erroneous code example:")?;
+        writeln!(f)?;
+        for line in self.example.lines() {
+            writeln!(f, "    {line}")?;
+        }
+        writeln!(f)?;
+        writeln!(f, "Fixed:")?;
+        writeln!(f)?;
+        for line in self.fix.lines() {
+            writeln!(f, "    {line}")?;
+        }
+        Ok(())
+    }
+}
+
+static EXPLANATIONS: &[Explanation] = &[
+    Explanation {
+        code: "L0001",
+        title: "Unexpected character",
+        description: "\
+The source contains a character that doesn't start any token. Lox only uses ASCII punctuation
+`(){};,.+-*/!=<>` and `\"`, along with letters, digits and `_` in names and numbers.",
+        example: "print 1 % 2;",
+        fix: "print 1 - 2 * 0;",
+    },
+    Explanation {
+        code: "L0002",
+        title: "Unterminated string",
+        description: "\
+A string literal is opened with `\"` but never closed before the end of the file. Strings may
+span several lines, so the scanner only gives up once it runs out of input. Editors and rich
+reports instead end the string at the end of the line it started on, and carry on scanning the
+lines after it as code.",
+        example: "print \"hello;",
+        fix: "print \"hello\";",
+    },
+    Explanation {
+        code: "L0003",
+        title: "Invalid escape sequence",
+        description: "\
+A backslash in a string literal is followed by something other than a known escape. The valid
+escapes are `\\n`, `\\t`, `\\r`, `\\\"`, `\\\\` and `\\u{XXXX}` with one to six hex digits naming
+a Unicode scalar value.",
+        example: "print \"C:\\Users\";",
+        fix: "print \"C:\\\\Users\";",
+    },
+    Explanation {
+        code: "L0004",
+        title: "Unterminated block comment",
+        description: "\
+A block comment opened with `/*` is never closed with `*/`. Block comments nest, so every
+`/*` inside a comment needs its own `*/` as well.",
+        example: "/* outer /* inner */\nprint 1;",
+        fix: "/* outer /* inner */ */\nprint 1;",
+    },
+    Explanation {
+        code: "L0005",
+        title: "Malformed number",
+        description: "\
+A number literal doesn't follow the number syntax. Hexadecimal, binary and octal numbers need
+digits of their base after `0x`, `0b` or `0o`, an exponent needs digits after `e`, and `_` may
+only separate two digits.",
+        example: "print 1_000_ + 0x;",
+        fix: "print 1_000 + 0x0;",
+    },
+    Explanation {
+        code: "L0006",
+        title: "Unexpected characters",
+        description: "\
+The source contains several characters in a row that don't start any token. Editors and rich
+reports list them as a single error rather than one L0001 per character.",
+        example: "print 1 @@ 2;",
+        fix: "print 1 == 2;",
+    },
+    Explanation {
+        code: "P0001",
+        title: "Expected expression",
+        description: "\
+The parser needs a value where there is none, or finds a token that can't start an
+expression, such as an operator missing its left operand.",
+        example: "print 1 + ;",
+        fix: "print 1 + 2;",
+    },
+    Explanation {
+        code: "P0002",
+        title: "Missing semicolon",
+        description: "\
+Expression, `print`, `var` and `return` statements end with `;`, as does the condition of a
+`for` loop. The error points at the token after the place the `;` belongs.",
+        example: "var a = 1\nprint a;",
+        fix: "var a = 1;\nprint a;",
+    },
+    Explanation {
+        code: "P0003",
+        title: "Missing parenthesis or brace",
+        description: "\
+A parenthesis or brace that the grammar requires is missing or unbalanced: around the
+condition of `if`, `while` and `for`, around arguments and parameters, around a grouping, or
+around the body of a block, function or class.",
+        example: "if (true {\n  print 1;\n}",
+        fix: "if (true) {\n  print 1;\n}",
+    },
+    Explanation {
+        code: "P0004",
+        title: "Expected name",
+        description: "\
+A name is missing where the grammar needs one: after `var`, `fun` and `class`, in a parameter
+list, after `<` in a class declaration, after `.` in a property access, and after `super.`,
+which can only be used to access a method.",
+        example: "var 1 = 2;",
+        fix: "var one = 2;",
+    },
+    Explanation {
+        code: "P0005",
+        title: "Invalid assignment target",
+        description: "\
+The left-hand side of `=` is neither a variable nor a property, so there is nothing to
+assign to. Only names, as in `a = 1`, and properties, as in `a.b = 1`, can be assigned.",
+        example: "var a = 1;\na + 1 = 2;",
+        fix: "var a = 1;\na = 2 - 1;",
+    },
+    Explanation {
+        code: "P0006",
+        title: "Too many arguments",
+        description: "\
+A call passes, or a function declares, more than 255 arguments or parameters. Group related
+values into an instance and pass that instead.",
+        example: "fun f(a0, a1, a2, ..., a255) {}",
+        fix: "class Args {}\nfun f(args) {}",
+    },
+    Explanation {
+        code: "P0007",
+        title: "Compiler limit exceeded",
+        description: "\
+The bytecode compiler can't fit a function in its chunk: it has more than 256 local
+variables in scope at once, captures more than 256 variables from enclosing functions, uses
+more than 256 constants, or has a body too large to jump over. Only the `vm` backend has these
+limits. Split the function into smaller ones.",
+        example: "fun f() {\n  var a0; var a1; ... var a256;\n}",
+        fix: "fun f() {\n  var a0; var a1;\n}",
+    },
+    Explanation {
+        code: "S0001",
+        title: "Variable read in its own initializer",
+        description: "\
+A local variable is used in the expression that initializes it. The variable isn't defined
+until that expression has been evaluated, so the read is refused rather than yielding `nil`
+or an outer variable of the same name.",
+        example: "var a = 1;\n{\n  var a = a + 1;\n}",
+        fix: "var a = 1;\n{\n  var b = a + 1;\n}",
+    },
+    Explanation {
+        code: "S0002",
+        title: "Variable already declared",
+        description: "\
+Two local variables or parameters of the same scope have the same name. Global variables can
+be declared again, but locals can't, as it is most likely a mistake.",
+        example: "fun f(a) {\n  var a = 1;\n}",
+        fix: "fun f(a) {\n  a = 1;\n}",
+    },
+    Explanation {
+        code: "S0003",
+        title: "Return outside of a function",
+        description: "\
+`return` is only meaningful inside a function or method. A script ends once its last
+statement has run.",
+        example: "return 1;",
+        fix: "fun f() {\n  return 1;\n}",
+    },
+    Explanation {
+        code: "S0004",
+        title: "Value returned from an initializer",
+        description: "\
+An `init` method always returns the instance being initialized, so `return` inside it can't
+have a value. A bare `return;` is allowed to end initialization early.",
+        example: "class A {\n  init() {\n    return 1;\n  }\n}",
+        fix: "class A {\n  init() {\n    return;\n  }\n}",
+    },
+    Explanation {
+        code: "S0005",
+        title: "`this` outside of a class",
+        description: "\
+`this` refers to the instance a method was called on, so it can only be used in methods.",
+        example: "fun f() {\n  print this;\n}",
+        fix: "class A {\n  f() {\n    print this;\n  }\n}",
+    },
+    Explanation {
+        code: "S0006",
+        title: "`super` outside of a class",
+        description: "\
+`super` accesses methods of the superclass of the class being declared, so it can only be
+used in methods.",
+        example: "fun f() {\n  super.f();\n}",
+        fix: "class A {}\nclass B < A {\n  f() {\n    super.f();\n  }\n}",
+    },
+    Explanation {
+        code: "S0007",
+        title: "`super` in a class without a superclass",
+        description: "\
+`super` is used in a method of a class that doesn't inherit from any other, so it has no
+superclass methods to refer to.",
+        example: "class A {\n  f() {\n    super.f();\n  }\n}",
+        fix: "class A {\n  f() {\n    this.g();\n  }\n}",
+    },
+    Explanation {
+        code: "S0008",
+        title: "Class inheriting from itself",
+        description: "\
+A class names itself as its own superclass, which would make looking up its methods loop
+forever.",
+        example: "class A < A {}",
+        fix: "class Base {}\nclass A < Base {}",
+    },
+    Explanation {
+        code: "R0001",
+        title: "Operand must be a number",
+        description: "\
+Unary `-`, and the operators `-`, `*`, `/`, `<`, `<=`, `>` and `>=`, only work on numbers.
+Lox never converts other values to numbers.",
+        example: "print -\"1\";",
+        fix: "print -1;",
+    },
+    Explanation {
+        code: "R0002",
+        title: "Operands must be two numbers or two strings",
+        description: "\
+`+` adds two numbers or concatenates two strings, and refuses anything else, including a
+number and a string.",
+        example: "print \"a\" + 1;",
+        fix: "print \"a\" + \"1\";",
+    },
+    Explanation {
+        code: "R0003",
+        title: "Undefined variable",
+        description: "\
+A variable is read or assigned before any declaration of it has run. Assigning a variable
+doesn't declare it, it needs `var` first.",
+        example: "a = 1;\nprint a;",
+        fix: "var a = 1;\nprint a;",
+    },
+    Explanation {
+        code: "R0004",
+        title: "Value not callable",
+        description: "\
+Only functions, methods and classes can be called. The value before `(` is something else,
+such as a number, a string or an instance.",
+        example: "var a = \"f\";\na();",
+        fix: "fun a() {}\na();",
+    },
+    Explanation {
+        code: "R0005",
+        title: "Wrong number of arguments",
+        description: "\
+A function is called with more or fewer arguments than it has parameters. Calling a class
+passes the arguments to its `init` method, or takes none if it has no `init`.",
+        example: "fun f(a, b) {}\nf(1);",
+        fix: "fun f(a, b) {}\nf(1, 2);",
+    },
+    Explanation {
+        code: "R0006",
+        title: "Not an instance",
+        description: "\
+Properties are accessed or set with `.` on something that isn't an instance of a class. Only
+instances have fields and methods.",
+        example: "var a = 1;\nprint a.b;",
+        fix: "class A {}\nvar a = A();\na.b = 1;\nprint a.b;",
+    },
+    Explanation {
+        code: "R0007",
+        title: "Undefined property",
+        description: "\
+An instance has neither a field nor a method by that name. Fields only exist once they have
+been assigned, usually in `init`.",
+        example: "class A {}\nprint A().b;",
+        fix: "class A {\n  init() {\n    this.b = 1;\n  }\n}\nprint A().b;",
+    },
+    Explanation {
+        code: "R0008",
+        title: "Superclass is not a class",
+        description: "\
+The name after `<` in a class declaration refers to a value that isn't a class, so there is
+nothing to inherit from.",
+        example: "var A = 1;\nclass B < A {}",
+        fix: "class A {}\nclass B < A {}",
+    },
+    Explanation {
+        code: "R0009",
+        title: "Stack overflow",
+        description: "\
+Calls are nested too deeply, most likely because a function calls itself without ever
+reaching a case that returns. Both backends give up at a depth of 16384 calls, counting the
+top-level code as one.",
+        example: "fun f(n) {\n  return f(n + 1);\n}\nf(0);",
+        fix: "fun f(n) {\n  if (n > 10) return n;\n  return f(n + 1);\n}\nf(0);",
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{vm::Errors, Diagnostic, Scanner, Vm};
+    use std::io;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn find() {
+        assert_eq!(
+            Explanation::find("L0002").unwrap().title,
+            "Unterminated string"
+        );
+        assert_eq!(Explanation::find("l0002"), Explanation::find("L0002"));
+        assert_eq!(Explanation::find("L9999"), None);
+    }
+
+    /// Code of the first error found in `source`, by the scanner, compiler or virtual machine.
+    fn first_error(source: &str) -> Option<&'static str> {
+        if let Some(e) = Scanner::new(source).with_recovery().find_map(Result::err) {
+            return e.code();
+        }
+        let mut vm = Vm::new(io::sink());
+        match vm.compile(source) {
+            Ok(function) => vm.interpret(function).err().and_then(|e| e.code()),
+            Err(Errors::Syntax(errors)) => errors[0].code(),
+            Err(Errors::Semantic(errors)) => errors[0].code(),
+        }
+    }
+
+    #[test]
+    fn examples() {
+        for explanation in Explanation::all() {
+            // Examples of limits are abridged.
+            if explanation.example.contains("...") {
+                continue;
+            }
+            assert_eq!(
+                first_error(explanation.example),
+                Some(explanation.code),
+                "{explanation}"
+            );
+            assert_eq!(first_error(explanation.fix), None, "{explanation}");
+        }
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            Explanation::find("L0004").unwrap().to_string(),
+            "\
+L0004: Unterminated block comment
+
+A block comment opened with `/*` is never closed with `*/`. Block comments nest, so every
+`/*` inside a comment needs its own `*/` as well.
+
+Erroneous code example:
+
+    /* outer /* inner */
+    print 1;
+
+Fixed:
+
+    /* outer /* inner */ */
+    print 1;
+"
+        );
+    }
+}
@@ -0,0 +1,916 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    io::{self, Write},
+    rc::Rc,
+};
+
+use thiserror::Error;
+
+use crate::{
+    ast,
+    callable::{Closure, Native},
+    class::{Class, Instance},
+    environment::Environment,
+    vm::FRAMES_MAX,
+    Diagnostic, Expr, Interner, Stmt, Symbol, Token, Type, Value,
+};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("{kind}\n[line {line}]")]
+    Runtime { line: usize, kind: RuntimeKind },
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+impl Error {
+    #[inline]
+    pub(crate) const fn runtime(line: usize, kind: RuntimeKind) -> Self {
+        Self::Runtime { line, kind }
+    }
+}
+
+impl Diagnostic for Error {
+    fn line(&self) -> usize {
+        match self {
+            Self::Runtime { line, .. } => *line,
+            Self::Io(_) => 0,
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            Self::Runtime { kind, .. } => kind.to_string(),
+            Self::Io(e) => e.to_string(),
+        }
+    }
+
+    fn code(&self) -> Option<&'static str> {
+        match self {
+            Self::Runtime { kind, .. } => Some(kind.code()),
+            Self::Io(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum RuntimeKind {
+    #[error("Operand must be a number.")]
+    OperandNotNumber,
+    #[error("Operands must be numbers.")]
+    OperandsNotNumbers,
+    #[error("Operands must be two numbers or two strings.")]
+    OperandsNotNumbersOrStrings,
+    #[error("Undefined variable '{0}'.")]
+    UndefinedVariable(String),
+    #[error("Can only call functions and classes.")]
+    NotCallable,
+    #[error("Expected {expected} arguments but got {got}.")]
+    Arity { expected: usize, got: usize },
+    #[error("Only instances have properties.")]
+    NotInstanceProperties,
+    #[error("Only instances have fields.")]
+    NotInstanceFields,
+    #[error("Undefined property '{0}'.")]
+    UndefinedProperty(String),
+    #[error("Superclass must be a class.")]
+    SuperclassNotClass,
+    #[error("Stack overflow.")]
+    StackOverflow,
+}
+
+impl RuntimeKind {
+    /// Stable code of this kind of error, never reused for another one.
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::OperandNotNumber | Self::OperandsNotNumbers => "R0001",
+            Self::OperandsNotNumbersOrStrings => "R0002",
+            Self::UndefinedVariable(_) => "R0003",
+            Self::NotCallable => "R0004",
+            Self::Arity { .. } => "R0005",
+            Self::NotInstanceProperties | Self::NotInstanceFields => "R0006",
+            Self::UndefinedProperty(_) => "R0007",
+            Self::SuperclassNotClass => "R0008",
+            Self::StackOverflow => "R0009",
+        }
+    }
+}
+
+/// Reasons for abandoning the execution of statements early.
+enum Unwind<'a> {
+    Return(Value<'a>),
+    Error(Error),
+}
+
+impl<T> From<T> for Unwind<'_>
+where
+    Error: From<T>,
+{
+    fn from(e: T) -> Self {
+        Self::Error(e.into())
+    }
+}
+
+pub struct Interpreter<'a, W> {
+    globals: Rc<RefCell<Environment<'a>>>,
+    environment: Rc<RefCell<Environment<'a>>>,
+    /// The [`Parser`](crate::Parser)'s names, which the syntax tree and environments refer to.
+    names: Interner,
+    this: Symbol,
+    super_: Symbol,
+    init: Symbol,
+    /// Number of calls to closures in progress.
+    depth: usize,
+    out: W,
+}
+
+impl<'a, W> Interpreter<'a, W>
+where
+    W: Write,
+{
+    /// Writes to `out`, running syntax trees whose names were interned in `names`.
+    #[must_use]
+    pub fn new(out: W, mut names: Interner) -> Self {
+        let globals = Rc::new(RefCell::new(Environment::default()));
+        for native in Native::ALL {
+            globals
+                .borrow_mut()
+                .define(names.intern(native.name), Value::Native(native));
+        }
+        Self {
+            environment: Rc::clone(&globals),
+            globals,
+            this: names.intern("this"),
+            super_: names.intern("super"),
+            init: names.intern("init"),
+            names,
+            depth: 0,
+            out,
+        }
+    }
+
+    /// Executes `statements`, which must have gone through the [`Resolver`](crate::Resolver).
+    pub fn interpret(&mut self, statements: &[Stmt<'a>]) -> Result<(), Error> {
+        let result = statements.iter().try_for_each(|stmt| self.execute(stmt));
+        self.out.flush()?;
+        match result {
+            Ok(()) | Err(Unwind::Return(_)) => Ok(()),
+            Err(Unwind::Error(e)) => Err(e),
+        }
+    }
+
+    fn execute(&mut self, stmt: &Stmt<'a>) -> Result<(), Unwind<'a>> {
+        // Every call of the program recurses through here, so arms needing more than a temporary
+        // or two live in functions of their own, keeping this frame small.
+        match stmt {
+            Stmt::Expression(expr) => self.evaluate(expr).map(drop).map_err(Unwind::Error),
+            Stmt::Print(expr) => self.print(expr).map_err(Unwind::Error),
+            Stmt::Var {
+                name, initializer, ..
+            } => self.var(*name, initializer.as_ref()).map_err(Unwind::Error),
+            Stmt::Block(statements) => {
+                let environment = Environment::new(Rc::clone(&self.environment));
+                self.execute_block(statements, environment)
+            }
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => self.if_(condition, then_branch, else_branch.as_deref()),
+            Stmt::While { condition, body } => self.while_(condition, body),
+            Stmt::Function(declaration) => {
+                self.function(declaration);
+                Ok(())
+            }
+            Stmt::Class(declaration) => self.class(declaration).map_err(Unwind::Error),
+            Stmt::Return { value, .. } => Err(self.return_(value.as_ref())),
+        }
+    }
+
+    fn if_(
+        &mut self,
+        condition: &Expr<'a>,
+        then_branch: &Stmt<'a>,
+        else_branch: Option<&Stmt<'a>>,
+    ) -> Result<(), Unwind<'a>> {
+        if self.evaluate(condition)?.is_truthy() {
+            self.execute(then_branch)
+        } else {
+            else_branch.map_or(Ok(()), |else_branch| self.execute(else_branch))
+        }
+    }
+
+    fn while_(&mut self, condition: &Expr<'a>, body: &Stmt<'a>) -> Result<(), Unwind<'a>> {
+        while self.evaluate(condition)?.is_truthy() {
+            self.execute(body)?;
+        }
+        Ok(())
+    }
+
+    /// Evaluates the value a `return` statement unwinds with.
+    fn return_(&mut self, value: Option<&Expr<'a>>) -> Unwind<'a> {
+        match value.map_or(Ok(Value::Nil), |x| self.evaluate(x)) {
+            Ok(value) => Unwind::Return(value),
+            Err(e) => Unwind::Error(e),
+        }
+    }
+
+    fn print(&mut self, expr: &Expr<'a>) -> Result<(), Error> {
+        let value = self.evaluate(expr)?;
+        writeln!(self.out, "{value}")?;
+        Ok(())
+    }
+
+    fn var(&mut self, name: Symbol, initializer: Option<&Expr<'a>>) -> Result<(), Error> {
+        let value = match initializer {
+            Some(x) => self.evaluate(x)?,
+            None => Value::Nil,
+        };
+        self.environment.borrow_mut().define(name, value);
+        Ok(())
+    }
+
+    fn function(&self, declaration: &Rc<ast::Function<'a>>) {
+        let closure = Closure::new(
+            self.names.resolve_shared(declaration.name),
+            Rc::clone(declaration),
+            Rc::clone(&self.environment),
+            false,
+        );
+        self.environment
+            .borrow_mut()
+            .define(declaration.name, Value::Closure(Rc::new(closure)));
+    }
+
+    fn class(&mut self, declaration: &ast::Class<'a>) -> Result<(), Error> {
+        let superclass = match &declaration.superclass {
+            Some(expr @ Expr::Variable { line, .. }) => match self.evaluate(expr)? {
+                Value::Class(x) => Some(x),
+                _ => return Err(Error::runtime(*line, RuntimeKind::SuperclassNotClass)),
+            },
+            _ => None,
+        };
+
+        self.environment
+            .borrow_mut()
+            .define(declaration.name, Value::Nil);
+
+        let environment = superclass.as_ref().map_or_else(
+            || Rc::clone(&self.environment),
+            |superclass| {
+                let mut environment = Environment::new(Rc::clone(&self.environment));
+                environment.define(self.super_, Value::Class(Rc::clone(superclass)));
+                Rc::new(RefCell::new(environment))
+            },
+        );
+        let methods = declaration
+            .methods
+            .iter()
+            .map(|method| {
+                let closure = Closure::new(
+                    self.names.resolve_shared(method.name),
+                    Rc::clone(method),
+                    Rc::clone(&environment),
+                    method.name == self.init,
+                );
+                (method.name, Rc::new(closure))
+            })
+            .collect::<HashMap<_, _>>();
+
+        let class = Class {
+            name: self.names.resolve_shared(declaration.name),
+            superclass,
+            methods,
+        };
+        self.environment
+            .borrow_mut()
+            .define(declaration.name, Value::Class(Rc::new(class)));
+        Ok(())
+    }
+
+    /// Executes `statements` in `environment`, restoring the current one afterwards even on error.
+    fn execute_block(
+        &mut self,
+        statements: &[Stmt<'a>],
+        environment: Environment<'a>,
+    ) -> Result<(), Unwind<'a>> {
+        let previous = std::mem::replace(&mut self.environment, Rc::new(RefCell::new(environment)));
+        let result = statements.iter().try_for_each(|stmt| self.execute(stmt));
+        self.environment = previous;
+        result
+    }
+
+    pub fn evaluate(&mut self, expr: &Expr<'a>) -> Result<Value<'a>, Error> {
+        // Kept small for the same reason as `execute`, each arm handing its result straight back.
+        match expr {
+            Expr::Nil => Ok(Value::Nil),
+            Expr::Bool(x) => Ok(Value::Bool(*x)),
+            Expr::Literal(x) => Ok(Value::from(x)),
+            Expr::Grouping(x) => self.evaluate(x),
+            Expr::Unary { op, right } => self.unary_expr(op, right),
+            Expr::Binary { left, op, right } => self.binary_expr(left, op, right),
+            Expr::Logical { left, op, right } => self.logical(left, op, right),
+            Expr::Variable { name, line, depth } => self
+                .look_up(*name, *depth)
+                .map_err(|kind| Error::runtime(*line, kind)),
+            Expr::Assign {
+                name,
+                line,
+                depth,
+                value,
+            } => self.assign_expr(*name, *line, *depth, value),
+            Expr::Call {
+                callee,
+                line,
+                arguments,
+            } => self.call_expr(callee, arguments, *line),
+            Expr::Get { object, name, line } => self.get(object, *name, *line),
+            Expr::Set {
+                object,
+                name,
+                line,
+                value,
+            } => self.set(object, *name, *line, value),
+            Expr::This { line, depth } => self
+                .look_up(self.this, *depth)
+                .map_err(|kind| Error::runtime(*line, kind)),
+            Expr::Super {
+                line,
+                depth,
+                method,
+            } => self
+                .super_method(*depth, *method)
+                .map_err(|kind| Error::runtime(*line, kind)),
+        }
+    }
+
+    fn unary_expr(&mut self, op: &Token, right: &Expr<'a>) -> Result<Value<'a>, Error> {
+        let right = self.evaluate(right)?;
+        Self::unary(op, &right)
+    }
+
+    fn binary_expr(
+        &mut self,
+        left: &Expr<'a>,
+        op: &Token,
+        right: &Expr<'a>,
+    ) -> Result<Value<'a>, Error> {
+        let left = self.evaluate(left)?;
+        let right = self.evaluate(right)?;
+        Self::binary(&left, op, &right)
+    }
+
+    fn logical(
+        &mut self,
+        left: &Expr<'a>,
+        op: &Token,
+        right: &Expr<'a>,
+    ) -> Result<Value<'a>, Error> {
+        let left = self.evaluate(left)?;
+        match op.ty {
+            Type::Or if left.is_truthy() => Ok(left),
+            Type::And if !left.is_truthy() => Ok(left),
+            _ => self.evaluate(right),
+        }
+    }
+
+    fn assign_expr(
+        &mut self,
+        name: Symbol,
+        line: usize,
+        depth: Option<usize>,
+        value: &Expr<'a>,
+    ) -> Result<Value<'a>, Error> {
+        let value = self.evaluate(value)?;
+        self.assign(name, depth, value.clone())
+            .map_err(|kind| Error::runtime(line, kind))?;
+        Ok(value)
+    }
+
+    fn call_expr(
+        &mut self,
+        callee: &Expr<'a>,
+        arguments: &[Expr<'a>],
+        line: usize,
+    ) -> Result<Value<'a>, Error> {
+        let callee = self.evaluate(callee)?;
+        let arguments = arguments
+            .iter()
+            .map(|x| self.evaluate(x))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.call(&callee, arguments, line)
+    }
+
+    fn get(&mut self, object: &Expr<'a>, name: Symbol, line: usize) -> Result<Value<'a>, Error> {
+        let Value::Instance(instance) = self.evaluate(object)? else {
+            return Err(Error::runtime(line, RuntimeKind::NotInstanceProperties));
+        };
+        Instance::get(&instance, name, self.this).ok_or_else(|| {
+            let name = self.names.resolve(name).to_owned();
+            Error::runtime(line, RuntimeKind::UndefinedProperty(name))
+        })
+    }
+
+    fn set(
+        &mut self,
+        object: &Expr<'a>,
+        name: Symbol,
+        line: usize,
+        value: &Expr<'a>,
+    ) -> Result<Value<'a>, Error> {
+        let Value::Instance(instance) = self.evaluate(object)? else {
+            return Err(Error::runtime(line, RuntimeKind::NotInstanceFields));
+        };
+        let value = self.evaluate(value)?;
+        instance.set(name, value.clone());
+        Ok(value)
+    }
+
+    fn look_up(&self, name: Symbol, depth: Option<usize>) -> Result<Value<'a>, RuntimeKind> {
+        depth
+            .map_or_else(
+                || self.globals.borrow().get(name),
+                |depth| self.environment.borrow().get_at(depth, name),
+            )
+            .ok_or_else(|| self.undefined(name))
+    }
+
+    fn assign(
+        &self,
+        name: Symbol,
+        depth: Option<usize>,
+        value: Value<'a>,
+    ) -> Result<(), RuntimeKind> {
+        let assigned = match depth {
+            Some(depth) => self.environment.borrow_mut().assign_at(depth, name, value),
+            None => self.globals.borrow_mut().assign(name, value),
+        };
+        if assigned {
+            Ok(())
+        } else {
+            Err(self.undefined(name))
+        }
+    }
+
+    fn undefined(&self, name: Symbol) -> RuntimeKind {
+        RuntimeKind::UndefinedVariable(self.names.resolve(name).to_owned())
+    }
+
+    fn super_method(&self, depth: Option<usize>, method: Symbol) -> Result<Value<'a>, RuntimeKind> {
+        let depth = depth.expect("'super' is always a local");
+        let environment = self.environment.borrow();
+        // `this` is always bound in the scope right inside the one defining `super`.
+        let (Some(Value::Class(superclass)), Some(Value::Instance(object))) = (
+            environment.get_at(depth, self.super_),
+            environment.get_at(depth - 1, self.this),
+        ) else {
+            unreachable!("'super' outside of a subclass method");
+        };
+        superclass
+            .find_method(method)
+            .map(|method| Value::Closure(Rc::new(method.bind(self.this, object))))
+            .ok_or_else(|| RuntimeKind::UndefinedProperty(self.names.resolve(method).to_owned()))
+    }
+
+    fn call(
+        &mut self,
+        callee: &Value<'a>,
+        arguments: Vec<Value<'a>>,
+        line: usize,
+    ) -> Result<Value<'a>, Error> {
+        let arity = match callee {
+            Value::Closure(x) => x.arity(),
+            Value::Native(x) => x.arity,
+            Value::Class(x) => x.arity(self.init),
+            _ => return Err(Error::runtime(line, RuntimeKind::NotCallable)),
+        };
+        if arguments.len() != arity {
+            return Err(Error::runtime(
+                line,
+                RuntimeKind::Arity {
+                    expected: arity,
+                    got: arguments.len(),
+                },
+            ));
+        }
+
+        match callee {
+            Value::Closure(x) => self.call_closure(x, arguments, line),
+            Value::Native(x) => Ok((x.fun)(&arguments)),
+            Value::Class(x) => {
+                let instance = Rc::new(Instance::new(Rc::clone(x)));
+                if let Some(init) = x.find_method(self.init) {
+                    self.call_closure(
+                        &init.bind(self.this, Rc::clone(&instance)),
+                        arguments,
+                        line,
+                    )?;
+                }
+                Ok(Value::Instance(instance))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Calls `closure` from `line`, overflowing the stack at the same depth as the
+    /// [`Vm`](crate::Vm).
+    fn call_closure(
+        &mut self,
+        closure: &Closure<'a>,
+        arguments: Vec<Value<'a>>,
+        line: usize,
+    ) -> Result<Value<'a>, Error> {
+        if self.depth + 1 >= FRAMES_MAX {
+            return Err(Error::runtime(line, RuntimeKind::StackOverflow));
+        }
+        let mut environment = Environment::new(Rc::clone(&closure.environment));
+        for (&(param, _), argument) in closure.declaration.params.iter().zip(arguments) {
+            environment.define(param, argument);
+        }
+        self.depth += 1;
+        let result = self.execute_block(&closure.declaration.body, environment);
+        self.depth -= 1;
+        let value = match result {
+            Ok(()) => Value::Nil,
+            Err(Unwind::Return(value)) => value,
+            Err(Unwind::Error(e)) => return Err(e),
+        };
+        if closure.is_initializer {
+            return Ok(closure
+                .environment
+                .borrow()
+                .get(self.this)
+                .expect("initializers are bound"));
+        }
+        Ok(value)
+    }
+
+    fn unary(op: &Token, right: &Value<'a>) -> Result<Value<'a>, Error> {
+        let line = op.span.line;
+        let value = match (op.ty, right) {
+            (Type::Bang, x) => Value::Bool(!x.is_truthy()),
+            (Type::Minus, Value::Number(x)) => Value::Number(-x),
+            (Type::Minus, _) => {
+                return Err(Error::runtime(line, RuntimeKind::OperandNotNumber));
+            }
+            _ => unreachable!("not an unary operator: {op}"),
+        };
+        Ok(value)
+    }
+
+    fn binary(left: &Value<'a>, op: &Token, right: &Value<'a>) -> Result<Value<'a>, Error> {
+        let line = op.span.line;
+        let value = match (left, op.ty, right) {
+            (l, Type::EqualEqual, r) => Value::Bool(l == r),
+            (l, Type::BangEqual, r) => Value::Bool(l != r),
+
+            (Value::Number(l), ty, Value::Number(r)) => match ty {
+                Type::Plus => Value::Number(l + r),
+                Type::Minus => Value::Number(l - r),
+                Type::Star => Value::Number(l * r),
+                Type::Slash => Value::Number(l / r),
+                Type::Greater => Value::Bool(l > r),
+                Type::GreaterEqual => Value::Bool(l >= r),
+                Type::Less => Value::Bool(l < r),
+                Type::LessEqual => Value::Bool(l <= r),
+                _ => unreachable!("not a binary operator: {op}"),
+            },
+            (Value::String(l), Type::Plus, Value::String(r)) => {
+                Value::String(Rc::from(format!("{l}{r}")))
+            }
+
+            (_, Type::Plus, _) => {
+                return Err(Error::runtime(
+                    line,
+                    RuntimeKind::OperandsNotNumbersOrStrings,
+                ));
+            }
+            _ => return Err(Error::runtime(line, RuntimeKind::OperandsNotNumbers)),
+        };
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Parser, Resolver};
+    use pretty_assertions::assert_eq;
+
+    fn evaluate(input: &str) -> Result<Value<'_>, (usize, RuntimeKind)> {
+        let mut parser = Parser::new(input);
+        let expr = parser.parse_expression().unwrap();
+        Interpreter::new(io::sink(), parser.into_names())
+            .evaluate(&expr)
+            .map_err(|e| match e {
+                Error::Runtime { line, kind } => (line, kind),
+                Error::Io(e) => panic!("{e}"),
+            })
+    }
+
+    fn run(input: &str) -> Result<String, (usize, RuntimeKind)> {
+        let mut parser = Parser::new(input);
+        let mut stmts = parser.parse().unwrap();
+        let mut names = parser.into_names();
+        Resolver::new(&mut names).resolve(&mut stmts).unwrap();
+        let mut out = Vec::new();
+        let result = Interpreter::new(&mut out, names).interpret(&stmts);
+        result.map_err(|e| match e {
+            Error::Runtime { line, kind } => (line, kind),
+            Error::Io(e) => panic!("{e}"),
+        })?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(evaluate("1 + 2 * 3").unwrap(), Value::Number(7.0));
+        assert_eq!(evaluate("(1 + 2) * 3").unwrap(), Value::Number(9.0));
+        assert_eq!(evaluate("10 / 4 - -1").unwrap(), Value::Number(3.5));
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(
+            evaluate("\"foo\" + \"bar\"").unwrap(),
+            Value::String(Rc::from("foobar"))
+        );
+        assert_eq!(evaluate("\"foo\" == \"foo\"").unwrap(), Value::Bool(true));
+    }
+
+    #[test]
+    fn logic() {
+        assert_eq!(evaluate("!nil").unwrap(), Value::Bool(true));
+        assert_eq!(evaluate("!0").unwrap(), Value::Bool(false));
+        assert_eq!(evaluate("1 < 2 == true").unwrap(), Value::Bool(true));
+        assert_eq!(evaluate("nil == false").unwrap(), Value::Bool(false));
+        assert_eq!(evaluate("\"1\" != 1").unwrap(), Value::Bool(true));
+        assert_eq!(evaluate("nil or \"yes\"").unwrap(), Value::from("yes"));
+        assert_eq!(evaluate("1 and nil").unwrap(), Value::Nil);
+        assert_eq!(evaluate("false and -nil").unwrap(), Value::Bool(false));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            evaluate("-\"foo\""),
+            Err((1, RuntimeKind::OperandNotNumber))
+        );
+        assert_eq!(
+            evaluate("1 +\n\"foo\""),
+            Err((1, RuntimeKind::OperandsNotNumbersOrStrings))
+        );
+        assert_eq!(
+            evaluate("\n1 < true"),
+            Err((2, RuntimeKind::OperandsNotNumbers))
+        );
+        assert_eq!(
+            evaluate("true * (1 + 2)").unwrap_err().1.to_string(),
+            "Operands must be numbers."
+        );
+    }
+
+    #[test]
+    fn variables() {
+        let input = "\
+        var a = 1;\n\
+        var b;\n\
+        print b;\n\
+        b = a = a + 1;\n\
+        print a + b;\n\
+        var a = \"shadowed\";\n\
+        print a;\n\
+        ";
+        assert_eq!(run(input).unwrap(), "nil\n4\nshadowed\n");
+        assert_eq!(
+            run("print 1;\n\nprint c;"),
+            Err((3, RuntimeKind::UndefinedVariable("c".to_owned())))
+        );
+        assert_eq!(
+            run("c = 1;"),
+            Err((1, RuntimeKind::UndefinedVariable("c".to_owned())))
+        );
+    }
+
+    #[test]
+    fn scopes() {
+        let input = "\
+        var a = \"global a\";\n\
+        var b = \"global b\";\n\
+        {\n\
+            var a = \"outer a\";\n\
+            {\n\
+                var a = \"inner a\";\n\
+                print a;\n\
+                b = \"assigned b\";\n\
+            }\n\
+            print a;\n\
+        }\n\
+        print a;\n\
+        print b;\n\
+        ";
+        assert_eq!(
+            run(input).unwrap(),
+            "inner a\nouter a\nglobal a\nassigned b\n"
+        );
+    }
+
+    #[test]
+    fn control_flow() {
+        let input = "\
+        var n = 0;\n\
+        while (n < 3) n = n + 1;\n\
+        if (n == 3) print \"three\"; else print \"not three\";\n\
+        for (var i = 0; i < 3; i = i + 1) { print i; }\n\
+        if (nil) print \"unreachable\";\n\
+        ";
+        assert_eq!(run(input).unwrap(), "three\n0\n1\n2\n");
+    }
+
+    #[test]
+    fn functions() {
+        let input = "\
+        fun fib(n) {\n\
+            if (n < 2) return n;\n\
+            return fib(n - 2) + fib(n - 1);\n\
+        }\n\
+        print fib(10);\n\
+        print fib;\n\
+        print clock;\n\
+        fun noop() {}\n\
+        print noop();\n\
+        print clock() > 0;\n\
+        ";
+        assert_eq!(
+            run(input).unwrap(),
+            "55\n<fn fib>\n<native fn>\nnil\ntrue\n"
+        );
+    }
+
+    #[test]
+    fn closures() {
+        let input = "\
+        fun makeCounter() {\n\
+            var i = 0;\n\
+            fun count() {\n\
+                i = i + 1;\n\
+                return i;\n\
+            }\n\
+            return count;\n\
+        }\n\
+        var a = makeCounter();\n\
+        var b = makeCounter();\n\
+        print a();\n\
+        print a();\n\
+        print b();\n\
+        ";
+        assert_eq!(run(input).unwrap(), "1\n2\n1\n");
+    }
+
+    #[test]
+    fn call_errors() {
+        assert_eq!(
+            run("\"not a function\"();"),
+            Err((1, RuntimeKind::NotCallable))
+        );
+        assert_eq!(
+            run("fun f(a, b) {}\nf(1);"),
+            Err((
+                2,
+                RuntimeKind::Arity {
+                    expected: 2,
+                    got: 1
+                }
+            ))
+        );
+        assert_eq!(
+            run("clock(1);"),
+            Err((
+                1,
+                RuntimeKind::Arity {
+                    expected: 0,
+                    got: 1
+                }
+            ))
+        );
+        assert_eq!(
+            RuntimeKind::Arity {
+                expected: 2,
+                got: 1
+            }
+            .to_string(),
+            "Expected 2 arguments but got 1."
+        );
+    }
+
+    #[test]
+    fn static_scope() {
+        let input = "\
+        var a = \"global\";\n\
+        {\n\
+            fun showA() { print a; }\n\
+            showA();\n\
+            var a = \"block\";\n\
+            showA();\n\
+            print a;\n\
+        }\n\
+        ";
+        assert_eq!(run(input).unwrap(), "global\nglobal\nblock\n");
+    }
+
+    #[test]
+    fn classes() {
+        let input = "\
+        class Point {\n\
+            init(x, y) {\n\
+                this.x = x;\n\
+                this.y = y;\n\
+            }\n\
+            sum() { return this.x + this.y; }\n\
+        }\n\
+        var p = Point(1, 2);\n\
+        print Point;\n\
+        print p;\n\
+        print p.sum();\n\
+        var sum = p.sum;\n\
+        p.x = 10;\n\
+        print sum();\n\
+        print p.init(3, 4) == p;\n\
+        print p.x;\n\
+        ";
+        assert_eq!(
+            run(input).unwrap(),
+            "Point\nPoint instance\n3\n12\ntrue\n3\n"
+        );
+    }
+
+    #[test]
+    fn initializer_early_return() {
+        let input = "\
+        class Foo {\n\
+            init() {\n\
+                this.a = 1;\n\
+                return;\n\
+                this.a = 2;\n\
+            }\n\
+        }\n\
+        var foo = Foo();\n\
+        print foo.a;\n\
+        print foo.init();\n\
+        ";
+        assert_eq!(run(input).unwrap(), "1\nFoo instance\n");
+    }
+
+    #[test]
+    fn inheritance() {
+        let input = "\
+        class A {\n\
+            method() { return \"A method\"; }\n\
+            name() { return \"A\"; }\n\
+        }\n\
+        class B < A {\n\
+            method() { return \"B method\"; }\n\
+            test() { return super.method() + \" \" + this.name(); }\n\
+        }\n\
+        class C < B {}\n\
+        print C().test();\n\
+        print C().method();\n\
+        ";
+        assert_eq!(run(input).unwrap(), "A method A\nB method\n");
+    }
+
+    #[test]
+    fn class_errors() {
+        assert_eq!(
+            run("class A {}\nprint A().missing;"),
+            Err((2, RuntimeKind::UndefinedProperty("missing".to_owned())))
+        );
+        assert_eq!(
+            run("var a = 1;\na.b = 2;"),
+            Err((2, RuntimeKind::NotInstanceFields))
+        );
+        assert_eq!(
+            run("\"str\".length;"),
+            Err((1, RuntimeKind::NotInstanceProperties))
+        );
+        assert_eq!(
+            run("var A = 1;\nclass B < A {}"),
+            Err((2, RuntimeKind::SuperclassNotClass))
+        );
+        assert_eq!(
+            run("class A {}\nclass B < A { f() { super.f(); } }\nB().f();"),
+            Err((2, RuntimeKind::UndefinedProperty("f".to_owned())))
+        );
+        assert_eq!(
+            run("class A { init(a) {} }\nA();"),
+            Err((
+                2,
+                RuntimeKind::Arity {
+                    expected: 1,
+                    got: 0
+                }
+            ))
+        );
+    }
+}
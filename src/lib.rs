@@ -1,11 +1,63 @@
 #![allow(clippy::missing_errors_doc)]
 
+mod diagnostic;
+pub use diagnostic::{Diagnostic, Renderer, Style};
+
+mod explain;
+pub use explain::Explanation;
+
 mod scanner;
 pub use scanner::Scanner;
 
+mod lossless;
+pub use lossless::{Lossless, LosslessToken};
+
+mod relex;
+pub use relex::{relex, Edit, Relexed, Scanned};
+
+mod stream;
+pub use stream::Stream;
+
+mod symbol;
+pub use symbol::{Interner, OwnedLiteral, OwnedToken, Symbol};
+
 mod token;
-pub use token::Token;
-pub(crate) use token::{Literal, Type};
+pub(crate) use token::Literal;
+pub use token::{Span, Token, Type};
 
 mod tokenize;
-pub use tokenize::Tokenizer;
+pub use tokenize::{Format, Outcome, Tokenizer};
+
+mod ast;
+pub use ast::{Expr, Stmt};
+
+mod parser;
+pub use parser::Parser;
+
+mod parse;
+pub use parse::AstPrinter;
+
+mod value;
+pub use value::Value;
+
+mod callable;
+mod class;
+mod environment;
+
+mod interpreter;
+pub use interpreter::Interpreter;
+
+mod evaluate;
+pub use evaluate::Evaluator;
+
+mod resolver;
+pub use resolver::Resolver;
+
+mod vm;
+pub use vm::Vm;
+
+mod disasm;
+pub use disasm::Disassembler;
+
+mod run;
+pub use run::{Backend, Runner};
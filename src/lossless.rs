@@ -0,0 +1,198 @@
+use std::fmt::Display;
+
+use crate::{scanner::Error, Scanner, Token};
+
+/// A token along with the whitespace and comments around it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LosslessToken<'a> {
+    leading: &'a str,
+    token: Token<'a>,
+    text: &'a str,
+    trailing: &'a str,
+}
+
+impl<'a> LosslessToken<'a> {
+    /// Trivia between the previous token's trailing trivia and this token, along with the text
+    /// of any rejected characters in between.
+    #[must_use]
+    pub const fn leading(&self) -> &'a str {
+        self.leading
+    }
+
+    #[must_use]
+    pub const fn token(&self) -> &Token<'a> {
+        &self.token
+    }
+
+    /// The token exactly as written in the source.
+    #[must_use]
+    pub const fn text(&self) -> &'a str {
+        self.text
+    }
+
+    /// Trivia after this token up to and including the end of its line.
+    #[must_use]
+    pub const fn trailing(&self) -> &'a str {
+        self.trailing
+    }
+}
+
+/// Concatenating every token of a [`Lossless`] scan reproduces its input.
+impl Display for LosslessToken<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}{}", self.leading, self.text, self.trailing)
+    }
+}
+
+/// Scans tokens without losing any of the input, see [`Scanner::lossless`].
+pub struct Lossless<'a> {
+    raw: &'a str,
+    scanner: Scanner<'a>,
+    /// Byte offset where the next token's leading trivia starts.
+    offset: usize,
+}
+
+impl<'a> Lossless<'a> {
+    pub(crate) const fn new(raw: &'a str, scanner: Scanner<'a>) -> Self {
+        Self {
+            raw,
+            scanner,
+            offset: 0,
+        }
+    }
+}
+
+impl<'a> Iterator for Lossless<'a> {
+    type Item = Result<LosslessToken<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let token = match self.scanner.next()? {
+            Ok(token) => token,
+            Err(e) => return Some(Err(e)),
+        };
+        let span = token.span();
+        let trailing = &self.raw[span.end..];
+        let trailing = &trailing[..trailing_len(trailing)];
+        let leading = &self.raw[self.offset..span.start];
+        self.offset = span.end + trailing.len();
+        Some(Ok(LosslessToken {
+            leading,
+            text: &self.raw[span.start..span.end],
+            token,
+            trailing,
+        }))
+    }
+}
+
+/// Length of the whitespace and comments at the start of `rest` that are still on the line of
+/// the token before them. Block comments spanning several lines are left to the next token.
+fn trailing_len(rest: &str) -> usize {
+    let mut len = 0;
+    while len < rest.len() {
+        let tail = &rest[len..];
+        if tail.starts_with('\n') {
+            return len + 1;
+        } else if tail.starts_with("//") {
+            return len + tail.find('\n').map_or(tail.len(), |i| i + 1);
+        } else if tail.starts_with("/*") {
+            let comment = &tail[..block_comment_len(tail)];
+            if comment.contains('\n') {
+                break;
+            }
+            len += comment.len();
+        } else if tail.starts_with([' ', '\t', '\r', '\x0C']) {
+            len += 1;
+        } else {
+            break;
+        }
+    }
+    len
+}
+
+/// Length of the possibly nested, possibly unterminated block comment at the start of `rest`.
+fn block_comment_len(rest: &str) -> usize {
+    let mut depth = 0;
+    let mut i = 0;
+    while i < rest.len() {
+        let tail = &rest[i..];
+        if tail.starts_with("/*") {
+            depth += 1;
+            i += 2;
+        } else if tail.starts_with("*/") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return i;
+            }
+        } else {
+            i += tail.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    rest.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Type;
+
+    use pretty_assertions::assert_eq;
+
+    fn round_trip(input: &str) -> String {
+        Scanner::new(input)
+            .lossless()
+            .filter_map(Result::ok)
+            .map(|token| token.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn round_trips() {
+        let programs = [
+            "",
+            "print 1;",
+            "  \n\n",
+            "// only a comment",
+            "var a = 1; // one\nvar b = 2;\r\n\r\nprint a + b;\n",
+            "fun f(x) {\n  /* nested /* block */\n  comment */ return x * 2;\n}\nprint f(21);",
+            "class A < B {\n\tinit() { this.s = \"multi\nline\"; }\n}\n",
+            "var café = \"\\u{e9}\\n\"; /**/ print café;",
+            "print @ 1 # 2; \"unterminated\n",
+            "/* unterminated /* comment */",
+        ];
+        for program in programs {
+            assert_eq!(round_trip(program), program);
+        }
+    }
+
+    #[test]
+    fn trivia() {
+        let input = "var a; // a\n\n/* b\n */ print /* c */ a;";
+        let tokens = Scanner::new(input)
+            .lossless()
+            .map(Result::unwrap)
+            .map(|token| (token.leading(), token.text(), token.trailing()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            [
+                ("", "var", " "),
+                ("", "a", ""),
+                ("", ";", " // a\n"),
+                ("\n/* b\n */ ", "print", " /* c */ "),
+                ("", "a", ""),
+                ("", ";", ""),
+                ("", "", ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn normalized_identifiers() {
+        let input = "cafe\u{301}";
+        let token = Scanner::new(input).lossless().next().unwrap().unwrap();
+        assert_eq!(token.token().ty, Type::Identifier);
+        assert_eq!(token.token().lexeme, "caf\u{e9}");
+        assert_eq!(token.text(), input);
+    }
+}
@@ -1,39 +1,191 @@
 use std::{
     fs,
-    io::{stderr, stdout},
-    path::PathBuf,
+    io::{stderr, stdout, Write},
+    path::{Path, PathBuf},
 };
 
 use anyhow::Context;
-use clap::{Parser, Subcommand};
-use interpreter_starter_rust::Tokenizer;
+use clap::{Parser, Subcommand, ValueEnum};
+use interpreter_starter_rust::{
+    AstPrinter, Backend, Disassembler, Evaluator, Explanation, Format, Runner, Style, Tokenizer,
+};
 
 #[derive(Debug, Parser)]
 struct Args {
     #[command(subcommand)]
     command: Command,
+    /// How errors are reported.
+    #[arg(long, global = true, value_enum, default_value = "plain")]
+    diagnostics: Diagnostics,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Diagnostics {
+    Plain,
+    Rich,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum TokenFormat {
+    Text,
+    Json,
+    Jsonl,
+}
+
+impl From<TokenFormat> for Format {
+    fn from(format: TokenFormat) -> Self {
+        match format {
+            TokenFormat::Text => Self::Text,
+            TokenFormat::Json => Self::Json,
+            TokenFormat::Jsonl => Self::Jsonl,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum RunBackend {
+    TreeWalk,
+    Vm,
+}
+
+impl From<RunBackend> for Backend {
+    fn from(backend: RunBackend) -> Self {
+        match backend {
+            RunBackend::TreeWalk => Self::TreeWalk,
+            RunBackend::Vm => Self::Vm,
+        }
+    }
+}
+
+impl From<Diagnostics> for Style {
+    fn from(diagnostics: Diagnostics) -> Self {
+        match diagnostics {
+            Diagnostics::Plain => Self::Plain,
+            Diagnostics::Rich => Self::Rich,
+        }
+    }
 }
 
 #[derive(Debug, Subcommand)]
 enum Command {
-    Tokenize { filename: PathBuf },
+    Tokenize {
+        filename: PathBuf,
+        /// How tokens and errors are written out.
+        #[arg(long, value_enum, default_value = "text")]
+        format: TokenFormat,
+        /// Stop at the first error.
+        #[arg(long)]
+        fail_fast: bool,
+        /// Report at most this many errors.
+        #[arg(long)]
+        max_errors: Option<usize>,
+    },
+    Parse {
+        filename: PathBuf,
+    },
+    Evaluate {
+        filename: PathBuf,
+    },
+    Run {
+        filename: PathBuf,
+        /// How the program is executed.
+        #[arg(long, value_enum, default_value = "tree-walk")]
+        backend: RunBackend,
+        /// Collect garbage before every allocation, with the `vm` backend.
+        #[arg(long)]
+        gc_stress: bool,
+    },
+    /// Prints the bytecode the program compiles to.
+    Disasm {
+        filename: PathBuf,
+    },
+    /// Describes an error code, such as `L0002`, in detail.
+    Explain {
+        code: String,
+    },
+}
+
+fn read(filename: &Path) -> anyhow::Result<String> {
+    fs::read_to_string(filename)
+        .with_context(|| format!("Failed to read file {}", filename.display()))
 }
 
 fn main() -> anyhow::Result<()> {
     let args = Args::parse();
     eprintln!("{args:#?}");
 
-    match args.command {
-        Command::Tokenize { filename } => {
-            let file_contents = fs::read_to_string(&filename)
-                .with_context(|| format!("Failed to read file {filename:?}"))?;
+    let mut stdout = stdout().lock();
+    let mut stderr = stderr().lock();
+    let style = Style::from(args.diagnostics);
 
-            let mut stdout = stdout().lock();
-            let mut stderr = stderr().lock();
-            let mut tokenizer = Tokenizer::new(&file_contents, &mut stdout, &mut stderr);
-            tokenizer.tokenize()?;
-            std::process::exit(tokenizer.code);
+    match args.command {
+        Command::Tokenize {
+            filename,
+            format,
+            fail_fast,
+            max_errors,
+        } => {
+            let file_contents = read(&filename)?;
+            let file = filename.display().to_string();
+            let mut tokenizer = Tokenizer::new(&file_contents, &mut stdout, &mut stderr)
+                .with_diagnostics(style, &file)
+                .with_format(format.into());
+            if fail_fast {
+                tokenizer = tokenizer.with_fail_fast();
+            }
+            if let Some(max) = max_errors {
+                tokenizer = tokenizer.with_max_errors(max);
+            }
+            if let Err(e) = tokenizer.tokenize() {
+                eprintln!("Failed to write tokens: {e}");
+            }
+            std::process::exit(tokenizer.outcome().code());
+        }
+        Command::Parse { filename } => {
+            let file_contents = read(&filename)?;
+            let file = filename.display().to_string();
+            let mut printer = AstPrinter::new(&file_contents, &mut stdout, &mut stderr)
+                .with_diagnostics(style, &file);
+            printer.print()?;
+            std::process::exit(printer.code);
+        }
+        Command::Evaluate { filename } => {
+            let file_contents = read(&filename)?;
+            let file = filename.display().to_string();
+            let mut evaluator = Evaluator::new(&file_contents, &mut stdout, &mut stderr)
+                .with_diagnostics(style, &file);
+            evaluator.evaluate()?;
+            std::process::exit(evaluator.code);
+        }
+        Command::Run {
+            filename,
+            backend,
+            gc_stress,
+        } => {
+            let file_contents = read(&filename)?;
+            let file = filename.display().to_string();
+            let mut runner = Runner::new(&file_contents, &mut stdout, &mut stderr)
+                .with_diagnostics(style, &file)
+                .with_backend(backend.into());
+            if gc_stress {
+                runner = runner.with_gc_stress();
+            }
+            runner.run()?;
+            std::process::exit(runner.code);
+        }
+        Command::Disasm { filename } => {
+            let file_contents = read(&filename)?;
+            let file = filename.display().to_string();
+            let mut disassembler = Disassembler::new(&file_contents, &mut stdout, &mut stderr)
+                .with_diagnostics(style, &file);
+            disassembler.disassemble()?;
+            std::process::exit(disassembler.code);
+        }
+        Command::Explain { code } => {
+            let explanation = Explanation::find(&code)
+                .with_context(|| format!("No explanation for error code {code}"))?;
+            write!(stdout, "{explanation}")?;
         }
     }
-    // Ok(())
+    Ok(())
 }
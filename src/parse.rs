@@ -0,0 +1,99 @@
+use std::io::{self, Write};
+
+use crate::{Parser, Renderer, Style};
+
+pub struct AstPrinter<'a, O, E> {
+    parser: Parser<'a>,
+    out: &'a mut O,
+    err: &'a mut E,
+    diagnostics: Renderer<'a>,
+    pub code: i32,
+}
+
+impl<'a, O, E> AstPrinter<'a, O, E>
+where
+    O: Write,
+    E: Write,
+{
+    pub fn new(input: &'a str, out: &'a mut O, err: &'a mut E) -> Self {
+        let parser = Parser::new(input);
+        Self {
+            parser,
+            out,
+            err,
+            diagnostics: Renderer::new(input),
+            code: 0,
+        }
+    }
+
+    /// Reports errors in `style`, naming `file` as the origin of the input.
+    #[must_use]
+    pub const fn with_diagnostics(mut self, style: Style, file: &'a str) -> Self {
+        self.diagnostics = self.diagnostics.with_style(style, file);
+        self
+    }
+
+    pub fn print(&mut self) -> io::Result<()> {
+        match self.parser.parse_expression() {
+            Ok(expr) => writeln!(self.out, "{}", expr.display(self.parser.names()))?,
+            Err(errors) => {
+                self.code = 65;
+                for e in errors {
+                    self.diagnostics.render(self.err, &e)?;
+                }
+            }
+        }
+
+        self.out.flush()?;
+        self.err.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn out() {
+        let mut buf = Vec::new();
+
+        let input = "(\"foo\" + -2) * 3 == nil";
+
+        let mut sink = io::sink();
+        let mut printer = AstPrinter::new(input, &mut buf, &mut sink);
+        printer.print().unwrap();
+        assert_eq!(printer.code, 0);
+
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "(== (* (group (+ foo (- 2.0))) 3.0) nil)\n"
+        );
+    }
+
+    #[test]
+    fn err() {
+        let mut out_buf = Vec::new();
+        let mut err_buf = Vec::new();
+
+        let input = "\
+        (1 +\n\
+        @)\n\
+        ";
+
+        let mut printer = AstPrinter::new(input, &mut out_buf, &mut err_buf);
+        printer.print().unwrap();
+        assert_eq!(printer.code, 65);
+
+        assert!(out_buf.is_empty());
+        assert_eq!(
+            String::from_utf8(err_buf).unwrap(),
+            "\
+            [line 2] Error: Unexpected character: @\n\
+            [line 2] Error at ')': Expect expression.\n\
+            "
+        );
+    }
+}
@@ -0,0 +1,1095 @@
+use std::{fmt::Display, rc::Rc};
+
+use thiserror::Error;
+
+use crate::{
+    ast::{Class, Function},
+    scanner, Diagnostic, Expr, Interner, Scanner, Span, Stmt, Token, Type,
+};
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum Error {
+    #[error(transparent)]
+    Lexical(#[from] scanner::Error),
+    #[error("[line {}] Error{at}: {kind}", span.line)]
+    Syntax {
+        span: Span,
+        at: Location,
+        kind: SyntaxKind,
+    },
+}
+
+impl Error {
+    #[inline]
+    pub(crate) fn syntax(token: &Token, kind: SyntaxKind) -> Self {
+        Self::Syntax {
+            span: token.span,
+            at: Location::from(token),
+            kind,
+        }
+    }
+}
+
+impl Diagnostic for Error {
+    fn line(&self) -> usize {
+        match self {
+            Self::Lexical(e) => e.line(),
+            Self::Syntax { span, .. } => span.line,
+        }
+    }
+
+    fn span(&self) -> Option<Span> {
+        match self {
+            Self::Lexical(e) => e.span(),
+            Self::Syntax { span, .. } => Some(*span),
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            Self::Lexical(e) => e.message(),
+            Self::Syntax { kind, .. } => kind.to_string(),
+        }
+    }
+
+    fn code(&self) -> Option<&'static str> {
+        match self {
+            Self::Lexical(e) => e.code(),
+            Self::Syntax { kind, .. } => Some(kind.code()),
+        }
+    }
+
+    fn help(&self) -> Option<&'static str> {
+        match self {
+            Self::Lexical(e) => e.help(),
+            Self::Syntax { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Location {
+    End,
+    Lexeme(String),
+}
+
+impl From<&Token<'_>> for Location {
+    fn from(token: &Token) -> Self {
+        match token.ty {
+            Type::Eof => Self::End,
+            _ => Self::Lexeme(token.lexeme.to_string()),
+        }
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::End => write!(f, " at end"),
+            Self::Lexeme(x) => write!(f, " at '{x}'"),
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SyntaxKind {
+    #[error("Expect expression.")]
+    ExpectExpression,
+    #[error("Expect ')' after expression.")]
+    ExpectRightParen,
+    #[error("Expect ';' after value.")]
+    ExpectSemicolonAfterValue,
+    #[error("Expect ';' after expression.")]
+    ExpectSemicolonAfterExpression,
+    #[error("Expect variable name.")]
+    ExpectVariableName,
+    #[error("Expect ';' after variable declaration.")]
+    ExpectSemicolonAfterVariable,
+    #[error("Invalid assignment target.")]
+    InvalidAssignmentTarget,
+    #[error("Expect '}}' after block.")]
+    ExpectRightBrace,
+    #[error("Expect '(' after 'if'.")]
+    ExpectLeftParenAfterIf,
+    #[error("Expect ')' after if condition.")]
+    ExpectRightParenAfterIf,
+    #[error("Expect '(' after 'while'.")]
+    ExpectLeftParenAfterWhile,
+    #[error("Expect ')' after condition.")]
+    ExpectRightParenAfterWhile,
+    #[error("Expect '(' after 'for'.")]
+    ExpectLeftParenAfterFor,
+    #[error("Expect ';' after loop condition.")]
+    ExpectSemicolonAfterLoopCondition,
+    #[error("Expect ')' after for clauses.")]
+    ExpectRightParenAfterFor,
+    #[error("Can't have more than {MAX_ARGS} arguments.")]
+    TooManyArguments,
+    #[error("Expect ')' after arguments.")]
+    ExpectRightParenAfterArguments,
+    #[error("Expect {0} name.")]
+    ExpectFunctionName(FunctionKind),
+    #[error("Expect '(' after {0} name.")]
+    ExpectLeftParenAfterFunctionName(FunctionKind),
+    #[error("Can't have more than {MAX_ARGS} parameters.")]
+    TooManyParameters,
+    #[error("Expect parameter name.")]
+    ExpectParameterName,
+    #[error("Expect ')' after parameters.")]
+    ExpectRightParenAfterParameters,
+    #[error("Expect '{{' before {0} body.")]
+    ExpectLeftBraceBeforeBody(FunctionKind),
+    #[error("Expect ';' after return value.")]
+    ExpectSemicolonAfterReturn,
+    #[error("Expect class name.")]
+    ExpectClassName,
+    #[error("Expect superclass name.")]
+    ExpectSuperclassName,
+    #[error("Expect '{{' before class body.")]
+    ExpectLeftBraceBeforeClassBody,
+    #[error("Expect '}}' after class body.")]
+    ExpectRightBraceAfterClassBody,
+    #[error("Expect property name after '.'.")]
+    ExpectPropertyName,
+    #[error("Expect '.' after 'super'.")]
+    ExpectDotAfterSuper,
+    #[error("Expect superclass method name.")]
+    ExpectSuperclassMethodName,
+    #[error("Too many local variables in function.")]
+    TooManyLocals,
+    #[error("Too many closure variables in function.")]
+    TooManyUpvalues,
+    #[error("Too many constants in one chunk.")]
+    TooManyConstants,
+    #[error("Too much code to jump over.")]
+    JumpTooLarge,
+    #[error("Loop body too large.")]
+    LoopTooLarge,
+}
+
+impl SyntaxKind {
+    /// Stable code of this kind of error, never reused for another one. Kinds that differ only
+    /// in what is missing share a code.
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::ExpectExpression => "P0001",
+            Self::ExpectSemicolonAfterValue
+            | Self::ExpectSemicolonAfterExpression
+            | Self::ExpectSemicolonAfterVariable
+            | Self::ExpectSemicolonAfterLoopCondition
+            | Self::ExpectSemicolonAfterReturn => "P0002",
+            Self::ExpectRightParen
+            | Self::ExpectRightBrace
+            | Self::ExpectLeftParenAfterIf
+            | Self::ExpectRightParenAfterIf
+            | Self::ExpectLeftParenAfterWhile
+            | Self::ExpectRightParenAfterWhile
+            | Self::ExpectLeftParenAfterFor
+            | Self::ExpectRightParenAfterFor
+            | Self::ExpectRightParenAfterArguments
+            | Self::ExpectLeftParenAfterFunctionName(_)
+            | Self::ExpectRightParenAfterParameters
+            | Self::ExpectLeftBraceBeforeBody(_)
+            | Self::ExpectLeftBraceBeforeClassBody
+            | Self::ExpectRightBraceAfterClassBody => "P0003",
+            Self::ExpectVariableName
+            | Self::ExpectFunctionName(_)
+            | Self::ExpectParameterName
+            | Self::ExpectClassName
+            | Self::ExpectSuperclassName
+            | Self::ExpectPropertyName
+            | Self::ExpectDotAfterSuper
+            | Self::ExpectSuperclassMethodName => "P0004",
+            Self::InvalidAssignmentTarget => "P0005",
+            Self::TooManyArguments | Self::TooManyParameters => "P0006",
+            Self::TooManyLocals
+            | Self::TooManyUpvalues
+            | Self::TooManyConstants
+            | Self::JumpTooLarge
+            | Self::LoopTooLarge => "P0007",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, strum_macros::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum FunctionKind {
+    Function,
+    Method,
+}
+
+/// Maximum number of arguments to a call, and parameters of a function.
+pub const MAX_ARGS: usize = 255;
+
+pub struct Parser<'a> {
+    scanner: Scanner<'a>,
+    current: Token<'a>,
+    errors: Vec<Error>,
+    /// Every name parsed, which the syntax tree refers to by [`Symbol`](crate::Symbol).
+    names: Interner,
+}
+
+impl<'a> Parser<'a> {
+    #[must_use]
+    pub fn new(input: &'a str) -> Self {
+        let mut parser = Self {
+            scanner: Scanner::new(input),
+            current: Token::EOF,
+            errors: Vec::new(),
+            names: Interner::new(),
+        };
+        parser.advance();
+        parser
+    }
+
+    /// The names the parsed syntax tree refers to.
+    #[must_use]
+    pub const fn names(&self) -> &Interner {
+        &self.names
+    }
+
+    /// Hands over the names the parsed syntax tree refers to, for resolving and running it.
+    #[must_use]
+    pub fn into_names(self) -> Interner {
+        self.names
+    }
+
+    /// Parses a single expression, returning every error encountered on the way.
+    pub fn parse_expression(&mut self) -> Result<Expr<'a>, Vec<Error>> {
+        match self.expression() {
+            Ok(expr) if self.errors.is_empty() => Ok(expr),
+            Ok(_) => Err(std::mem::take(&mut self.errors)),
+            Err(e) => {
+                self.errors.push(e);
+                Err(std::mem::take(&mut self.errors))
+            }
+        }
+    }
+
+    /// Parses a whole program, recovering after each syntax error so that all of them get reported.
+    pub fn parse(&mut self) -> Result<Vec<Stmt<'a>>, Vec<Error>> {
+        let mut statements = Vec::new();
+        while self.current.ty != Type::Eof {
+            match self.declaration() {
+                Ok(stmt) => statements.push(stmt),
+                Err(e) => {
+                    self.errors.push(e);
+                    self.synchronize();
+                }
+            }
+        }
+
+        if self.errors.is_empty() {
+            Ok(statements)
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    /// Discards tokens until the start of the next statement.
+    fn synchronize(&mut self) {
+        while self.current.ty != Type::Eof {
+            let previous = self.advance();
+            if previous.ty == Type::Semicolon {
+                return;
+            }
+            if matches!(
+                self.current.ty,
+                Type::Class
+                    | Type::Fun
+                    | Type::Var
+                    | Type::For
+                    | Type::If
+                    | Type::While
+                    | Type::Print
+                    | Type::Return
+            ) {
+                return;
+            }
+        }
+    }
+
+    /// Moves to the next valid token, collecting lexical errors along the way.
+    fn advance(&mut self) -> Token<'a> {
+        let previous = self.current.clone();
+        for token in self.scanner.by_ref() {
+            match token {
+                Ok(token) => {
+                    self.current = token;
+                    break;
+                }
+                Err(e) => self.errors.push(e.into()),
+            }
+        }
+        previous
+    }
+
+    fn next_if(&mut self, types: &[Type]) -> Option<Token<'a>> {
+        types.contains(&self.current.ty).then(|| self.advance())
+    }
+
+    fn expect(&mut self, ty: Type, kind: SyntaxKind) -> Result<Token<'a>, Error> {
+        self.next_if(&[ty]).ok_or_else(|| self.error(kind))
+    }
+
+    fn error(&self, kind: SyntaxKind) -> Error {
+        Error::syntax(&self.current, kind)
+    }
+
+    fn declaration(&mut self) -> Result<Stmt<'a>, Error> {
+        if self.next_if(&[Type::Var]).is_some() {
+            return self.var_declaration();
+        }
+        if self.next_if(&[Type::Fun]).is_some() {
+            return self
+                .function(FunctionKind::Function)
+                .map(|x| Stmt::Function(Rc::new(x)));
+        }
+        if self.next_if(&[Type::Class]).is_some() {
+            return self.class_declaration().map(Stmt::Class);
+        }
+        self.statement()
+    }
+
+    fn class_declaration(&mut self) -> Result<Class<'a>, Error> {
+        let name = self.expect(Type::Identifier, SyntaxKind::ExpectClassName)?;
+
+        let superclass = match self.next_if(&[Type::Less]) {
+            Some(_) => {
+                let name = self.expect(Type::Identifier, SyntaxKind::ExpectSuperclassName)?;
+                Some(Expr::Variable {
+                    name: self.names.intern(&name.lexeme),
+                    line: name.span.line,
+                    depth: None,
+                })
+            }
+            None => None,
+        };
+
+        self.expect(Type::LeftBrace, SyntaxKind::ExpectLeftBraceBeforeClassBody)?;
+        let mut methods = Vec::new();
+        while !matches!(self.current.ty, Type::RightBrace | Type::Eof) {
+            methods.push(Rc::new(self.function(FunctionKind::Method)?));
+        }
+        self.expect(Type::RightBrace, SyntaxKind::ExpectRightBraceAfterClassBody)?;
+
+        Ok(Class {
+            name: self.names.intern(&name.lexeme),
+            line: name.span.line,
+            superclass,
+            methods,
+        })
+    }
+
+    fn function(&mut self, kind: FunctionKind) -> Result<Function<'a>, Error> {
+        let name = self.expect(Type::Identifier, SyntaxKind::ExpectFunctionName(kind))?;
+        self.expect(
+            Type::LeftParen,
+            SyntaxKind::ExpectLeftParenAfterFunctionName(kind),
+        )?;
+
+        let mut params = Vec::new();
+        if self.current.ty != Type::RightParen {
+            loop {
+                if params.len() >= MAX_ARGS {
+                    self.errors.push(self.error(SyntaxKind::TooManyParameters));
+                }
+                let param = self.expect(Type::Identifier, SyntaxKind::ExpectParameterName)?;
+                params.push((self.names.intern(&param.lexeme), param.span.line));
+                if self.next_if(&[Type::Comma]).is_none() {
+                    break;
+                }
+            }
+        }
+        self.expect(
+            Type::RightParen,
+            SyntaxKind::ExpectRightParenAfterParameters,
+        )?;
+
+        self.expect(Type::LeftBrace, SyntaxKind::ExpectLeftBraceBeforeBody(kind))?;
+        let body = self.block()?;
+        Ok(Function {
+            name: self.names.intern(&name.lexeme),
+            line: name.span.line,
+            params,
+            body,
+        })
+    }
+
+    fn var_declaration(&mut self) -> Result<Stmt<'a>, Error> {
+        let name = self.expect(Type::Identifier, SyntaxKind::ExpectVariableName)?;
+        let initializer = self
+            .next_if(&[Type::Equal])
+            .map(|_| self.expression())
+            .transpose()?;
+        self.expect(Type::Semicolon, SyntaxKind::ExpectSemicolonAfterVariable)?;
+        Ok(Stmt::Var {
+            name: self.names.intern(&name.lexeme),
+            line: name.span.line,
+            initializer,
+        })
+    }
+
+    fn statement(&mut self) -> Result<Stmt<'a>, Error> {
+        let Some(token) = self.next_if(&[
+            Type::Print,
+            Type::LeftBrace,
+            Type::If,
+            Type::While,
+            Type::For,
+            Type::Return,
+        ]) else {
+            let expr = self.expression()?;
+            self.expect(Type::Semicolon, SyntaxKind::ExpectSemicolonAfterExpression)?;
+            return Ok(Stmt::Expression(expr));
+        };
+
+        match token.ty {
+            Type::Print => {
+                let value = self.expression()?;
+                self.expect(Type::Semicolon, SyntaxKind::ExpectSemicolonAfterValue)?;
+                Ok(Stmt::Print(value))
+            }
+            Type::LeftBrace => self.block().map(Stmt::Block),
+            Type::If => self.if_statement(),
+            Type::While => self.while_statement(),
+            Type::For => self.for_statement(),
+            Type::Return => {
+                let value = match self.current.ty {
+                    Type::Semicolon => None,
+                    _ => Some(self.expression()?),
+                };
+                self.expect(Type::Semicolon, SyntaxKind::ExpectSemicolonAfterReturn)?;
+                Ok(Stmt::Return {
+                    line: token.span.line,
+                    value,
+                })
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Parses the rest of a block whose `{` was already consumed.
+    fn block(&mut self) -> Result<Vec<Stmt<'a>>, Error> {
+        let mut statements = Vec::new();
+        while !matches!(self.current.ty, Type::RightBrace | Type::Eof) {
+            statements.push(self.declaration()?);
+        }
+        self.expect(Type::RightBrace, SyntaxKind::ExpectRightBrace)?;
+        Ok(statements)
+    }
+
+    fn if_statement(&mut self) -> Result<Stmt<'a>, Error> {
+        self.expect(Type::LeftParen, SyntaxKind::ExpectLeftParenAfterIf)?;
+        let condition = self.expression()?;
+        self.expect(Type::RightParen, SyntaxKind::ExpectRightParenAfterIf)?;
+
+        let then_branch = Box::new(self.statement()?);
+        let else_branch = self
+            .next_if(&[Type::Else])
+            .map(|_| self.statement().map(Box::new))
+            .transpose()?;
+        Ok(Stmt::If {
+            condition,
+            then_branch,
+            else_branch,
+        })
+    }
+
+    fn while_statement(&mut self) -> Result<Stmt<'a>, Error> {
+        self.expect(Type::LeftParen, SyntaxKind::ExpectLeftParenAfterWhile)?;
+        let condition = self.expression()?;
+        self.expect(Type::RightParen, SyntaxKind::ExpectRightParenAfterWhile)?;
+        let body = Box::new(self.statement()?);
+        Ok(Stmt::While { condition, body })
+    }
+
+    /// Desugars a `for` loop into a `while` loop wrapped in a block.
+    fn for_statement(&mut self) -> Result<Stmt<'a>, Error> {
+        self.expect(Type::LeftParen, SyntaxKind::ExpectLeftParenAfterFor)?;
+
+        let initializer = match self.current.ty {
+            Type::Semicolon => {
+                self.advance();
+                None
+            }
+            Type::Var => {
+                self.advance();
+                Some(self.var_declaration()?)
+            }
+            _ => {
+                let expr = self.expression()?;
+                self.expect(Type::Semicolon, SyntaxKind::ExpectSemicolonAfterExpression)?;
+                Some(Stmt::Expression(expr))
+            }
+        };
+
+        let condition = match self.current.ty {
+            Type::Semicolon => Expr::Bool(true),
+            _ => self.expression()?,
+        };
+        self.expect(
+            Type::Semicolon,
+            SyntaxKind::ExpectSemicolonAfterLoopCondition,
+        )?;
+
+        let increment = match self.current.ty {
+            Type::RightParen => None,
+            _ => Some(self.expression()?),
+        };
+        self.expect(Type::RightParen, SyntaxKind::ExpectRightParenAfterFor)?;
+
+        let mut body = self.statement()?;
+        if let Some(increment) = increment {
+            body = Stmt::Block(vec![body, Stmt::Expression(increment)]);
+        }
+        body = Stmt::While {
+            condition,
+            body: Box::new(body),
+        };
+        if let Some(initializer) = initializer {
+            body = Stmt::Block(vec![initializer, body]);
+        }
+        Ok(body)
+    }
+
+    fn expression(&mut self) -> Result<Expr<'a>, Error> {
+        self.assignment()
+    }
+
+    fn assignment(&mut self) -> Result<Expr<'a>, Error> {
+        let expr = self.or()?;
+
+        if let Some(equals) = self.next_if(&[Type::Equal]) {
+            let value = self.assignment()?;
+            match expr {
+                Expr::Variable {
+                    name, line, depth, ..
+                } => {
+                    return Ok(Expr::Assign {
+                        name,
+                        line,
+                        depth,
+                        value: Box::new(value),
+                    });
+                }
+                Expr::Get { object, name, line } => {
+                    return Ok(Expr::Set {
+                        object,
+                        name,
+                        line,
+                        value: Box::new(value),
+                    });
+                }
+                _ => (),
+            }
+            // Reported without unwinding, the parser is not in a confused state.
+            self.errors
+                .push(Error::syntax(&equals, SyntaxKind::InvalidAssignmentTarget));
+        }
+        Ok(expr)
+    }
+
+    fn logical(
+        &mut self,
+        ty: Type,
+        operand: fn(&mut Self) -> Result<Expr<'a>, Error>,
+    ) -> Result<Expr<'a>, Error> {
+        let mut expr = operand(self)?;
+        while let Some(op) = self.next_if(&[ty]) {
+            let right = operand(self)?;
+            expr = Expr::Logical {
+                left: Box::new(expr),
+                op,
+                right: Box::new(right),
+            };
+        }
+        Ok(expr)
+    }
+
+    fn or(&mut self) -> Result<Expr<'a>, Error> {
+        self.logical(Type::Or, Self::and)
+    }
+
+    fn and(&mut self) -> Result<Expr<'a>, Error> {
+        self.logical(Type::And, Self::equality)
+    }
+
+    fn binary(
+        &mut self,
+        types: &[Type],
+        operand: fn(&mut Self) -> Result<Expr<'a>, Error>,
+    ) -> Result<Expr<'a>, Error> {
+        let mut expr = operand(self)?;
+        while let Some(op) = self.next_if(types) {
+            let right = operand(self)?;
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                op,
+                right: Box::new(right),
+            };
+        }
+        Ok(expr)
+    }
+
+    fn equality(&mut self) -> Result<Expr<'a>, Error> {
+        self.binary(&[Type::BangEqual, Type::EqualEqual], Self::comparison)
+    }
+
+    fn comparison(&mut self) -> Result<Expr<'a>, Error> {
+        self.binary(
+            &[
+                Type::Greater,
+                Type::GreaterEqual,
+                Type::Less,
+                Type::LessEqual,
+            ],
+            Self::term,
+        )
+    }
+
+    fn term(&mut self) -> Result<Expr<'a>, Error> {
+        self.binary(&[Type::Minus, Type::Plus], Self::factor)
+    }
+
+    fn factor(&mut self) -> Result<Expr<'a>, Error> {
+        self.binary(&[Type::Slash, Type::Star], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Expr<'a>, Error> {
+        if let Some(op) = self.next_if(&[Type::Bang, Type::Minus]) {
+            let right = self.unary()?;
+            return Ok(Expr::Unary {
+                op,
+                right: Box::new(right),
+            });
+        }
+        self.call()
+    }
+
+    fn call(&mut self) -> Result<Expr<'a>, Error> {
+        let mut expr = self.primary()?;
+        while let Some(token) = self.next_if(&[Type::LeftParen, Type::Dot]) {
+            if token.ty == Type::Dot {
+                let name = self.expect(Type::Identifier, SyntaxKind::ExpectPropertyName)?;
+                expr = Expr::Get {
+                    object: Box::new(expr),
+                    name: self.names.intern(&name.lexeme),
+                    line: name.span.line,
+                };
+                continue;
+            }
+
+            let mut arguments = Vec::new();
+            if self.current.ty != Type::RightParen {
+                loop {
+                    if arguments.len() >= MAX_ARGS {
+                        self.errors.push(self.error(SyntaxKind::TooManyArguments));
+                    }
+                    arguments.push(self.expression()?);
+                    if self.next_if(&[Type::Comma]).is_none() {
+                        break;
+                    }
+                }
+            }
+            let paren =
+                self.expect(Type::RightParen, SyntaxKind::ExpectRightParenAfterArguments)?;
+            expr = Expr::Call {
+                callee: Box::new(expr),
+                line: paren.span.line,
+                arguments,
+            };
+        }
+        Ok(expr)
+    }
+
+    fn primary(&mut self) -> Result<Expr<'a>, Error> {
+        let expr = match self.current.ty {
+            Type::False => Expr::Bool(false),
+            Type::True => Expr::Bool(true),
+            Type::Nil => Expr::Nil,
+            Type::Number | Type::String => Expr::Literal(self.current.literal.clone()),
+            Type::Identifier => Expr::Variable {
+                name: self.names.intern(&self.current.lexeme),
+                line: self.current.span.line,
+                depth: None,
+            },
+            Type::This => Expr::This {
+                line: self.current.span.line,
+                depth: None,
+            },
+            Type::Super => {
+                let keyword = self.advance();
+                self.expect(Type::Dot, SyntaxKind::ExpectDotAfterSuper)?;
+                let method =
+                    self.expect(Type::Identifier, SyntaxKind::ExpectSuperclassMethodName)?;
+                return Ok(Expr::Super {
+                    line: keyword.span.line,
+                    depth: None,
+                    method: self.names.intern(&method.lexeme),
+                });
+            }
+            Type::LeftParen => {
+                self.advance();
+                let expr = self.expression()?;
+                self.expect(Type::RightParen, SyntaxKind::ExpectRightParen)?;
+                return Ok(Expr::Grouping(Box::new(expr)));
+            }
+            _ => return Err(self.error(SyntaxKind::ExpectExpression)),
+        };
+        self.advance();
+        Ok(expr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{scanner::LexicalKind, Literal};
+    use pretty_assertions::assert_eq;
+
+    fn syntax(token: Token, start: usize, line: usize, column: usize, kind: SyntaxKind) -> Error {
+        let span = Span {
+            start,
+            end: start + token.lexeme.len(),
+            line,
+            column,
+        };
+        Error::syntax(&token.with_span(span), kind)
+    }
+
+    fn parse(input: &str) -> Result<String, Vec<Error>> {
+        let mut parser = Parser::new(input);
+        parser
+            .parse_expression()
+            .map(|expr| expr.display(parser.names()).to_string())
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(parse("true").unwrap(), "true");
+        assert_eq!(parse("false").unwrap(), "false");
+        assert_eq!(parse("nil").unwrap(), "nil");
+        assert_eq!(parse("42").unwrap(), "42.0");
+        assert_eq!(parse("0.5").unwrap(), "0.5");
+        assert_eq!(parse("\"hello world\"").unwrap(), "hello world");
+    }
+
+    #[test]
+    fn grouping() {
+        assert_eq!(parse("(\"foo\")").unwrap(), "(group foo)");
+        assert_eq!(parse("((true))").unwrap(), "(group (group true))");
+    }
+
+    #[test]
+    fn unary() {
+        assert_eq!(parse("!true").unwrap(), "(! true)");
+        assert_eq!(parse("--1").unwrap(), "(- (- 1.0))");
+        assert_eq!(parse("!(!false)").unwrap(), "(! (group (! false)))");
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(parse("1 + 2 * 3").unwrap(), "(+ 1.0 (* 2.0 3.0))");
+        assert_eq!(parse("1 - 2 - 3").unwrap(), "(- (- 1.0 2.0) 3.0)");
+        assert_eq!(
+            parse("(1 + 2) / -3").unwrap(),
+            "(/ (group (+ 1.0 2.0)) (- 3.0))"
+        );
+        assert_eq!(
+            parse("1 < 2 == 3 >= 4 != true").unwrap(),
+            "(!= (== (< 1.0 2.0) (>= 3.0 4.0)) true)"
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            parse("(72 +)").unwrap_err(),
+            [syntax(
+                Token::RIGHT_PAREN,
+                5,
+                1,
+                6,
+                SyntaxKind::ExpectExpression
+            )]
+        );
+        assert_eq!(
+            parse("(\"foo\"\n").unwrap_err(),
+            [syntax(Token::EOF, 7, 2, 1, SyntaxKind::ExpectRightParen)]
+        );
+        assert_eq!(
+            parse("1 + $").unwrap_err(),
+            [
+                Error::from(scanner::Error::lexical(
+                    Span {
+                        start: 4,
+                        end: 5,
+                        line: 1,
+                        column: 5
+                    },
+                    LexicalKind::UnexpectedCharacter('$')
+                )),
+                syntax(Token::EOF, 5, 1, 6, SyntaxKind::ExpectExpression)
+            ]
+        );
+    }
+
+    #[test]
+    fn assignment() {
+        assert_eq!(parse("a = b = 1").unwrap(), "(= a (= b 1.0))");
+        assert_eq!(parse("a or b and c").unwrap(), "(or a (and b c))");
+        assert_eq!(
+            parse("a + b = c").unwrap_err(),
+            [syntax(
+                Token::EQUAL,
+                6,
+                1,
+                7,
+                SyntaxKind::InvalidAssignmentTarget
+            )]
+        );
+    }
+
+    #[test]
+    fn statements() {
+        let input = "\
+        var a = 1;\n\
+        { print a; a; }\n\
+        if (a) print 1; else print 2;\n\
+        while (false) {}\n\
+        ";
+        let mut parser = Parser::new(input);
+        let stmts = parser.parse().unwrap();
+        let a = parser.names().get("a").unwrap();
+        assert_eq!(
+            stmts,
+            [
+                Stmt::Var {
+                    name: a,
+                    line: 1,
+                    initializer: Some(Expr::Literal(Literal::Number(1.0)))
+                },
+                Stmt::Block(vec![
+                    Stmt::Print(Expr::Variable {
+                        name: a,
+                        line: 2,
+                        depth: None
+                    }),
+                    Stmt::Expression(Expr::Variable {
+                        name: a,
+                        line: 2,
+                        depth: None
+                    }),
+                ]),
+                Stmt::If {
+                    condition: Expr::Variable {
+                        name: a,
+                        line: 3,
+                        depth: None
+                    },
+                    then_branch: Box::new(Stmt::Print(Expr::Literal(Literal::Number(1.0)))),
+                    else_branch: Some(Box::new(Stmt::Print(Expr::Literal(Literal::Number(2.0))))),
+                },
+                Stmt::While {
+                    condition: Expr::Bool(false),
+                    body: Box::new(Stmt::Block(Vec::new())),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn for_desugaring() {
+        let desugared = Parser::new("for (var i = 0; i < 1; i = i + 1) print i;")
+            .parse()
+            .unwrap();
+        let expected = Parser::new("{ var i = 0; while (i < 1) { print i; i = i + 1; } }")
+            .parse()
+            .unwrap();
+        assert_eq!(desugared, expected);
+
+        let desugared = Parser::new("for (;;) print 1;").parse().unwrap();
+        let expected = Parser::new("while (true) print 1;").parse().unwrap();
+        assert_eq!(desugared, expected);
+    }
+
+    #[test]
+    fn synchronize() {
+        let input = "\
+        print 1\n\
+        var a = 2;\n\
+        var = 3;\n\
+        { print 4;\n\
+        ";
+        assert_eq!(
+            Parser::new(input).parse().unwrap_err(),
+            [
+                syntax(Token::VAR, 8, 2, 1, SyntaxKind::ExpectSemicolonAfterValue),
+                syntax(Token::EQUAL, 23, 3, 5, SyntaxKind::ExpectVariableName),
+                syntax(Token::EOF, 39, 5, 1, SyntaxKind::ExpectRightBrace),
+            ]
+        );
+    }
+
+    #[test]
+    fn calls() {
+        assert_eq!(parse("f()").unwrap(), "(call f)");
+        assert_eq!(
+            parse("f(1, g(2))(3)").unwrap(),
+            "(call (call f 1.0 (call g 2.0)) 3.0)"
+        );
+        assert_eq!(
+            parse("f(1,)").unwrap_err(),
+            [syntax(
+                Token::RIGHT_PAREN,
+                4,
+                1,
+                5,
+                SyntaxKind::ExpectExpression
+            )]
+        );
+
+        let arguments = vec!["a"; MAX_ARGS + 1].join(", ");
+        assert_eq!(
+            parse(&format!("f({arguments})")).unwrap_err(),
+            [syntax(
+                Token::new_null(Type::Identifier, "a"),
+                767,
+                1,
+                768,
+                SyntaxKind::TooManyArguments
+            )]
+        );
+    }
+
+    #[test]
+    fn functions() {
+        let input = "\
+        fun add(a, b) {\n\
+            return a + b;\n\
+        }\n\
+        fun noop() { return; }\n\
+        ";
+        let mut parser = Parser::new(input);
+        let stmts = parser.parse().unwrap();
+        let names = parser.names();
+        let Stmt::Function(add) = &stmts[0] else {
+            panic!("not a function: {:?}", stmts[0]);
+        };
+        assert_eq!(names.resolve(add.name), "add");
+        assert_eq!(
+            add.params,
+            [(names.get("a").unwrap(), 1), (names.get("b").unwrap(), 1)]
+        );
+        assert!(matches!(
+            add.body[..],
+            [Stmt::Return {
+                line: 2,
+                value: Some(_)
+            }]
+        ));
+
+        let Stmt::Function(noop) = &stmts[1] else {
+            panic!("not a function: {:?}", stmts[1]);
+        };
+        assert!(noop.params.is_empty());
+        assert_eq!(
+            noop.body,
+            [Stmt::Return {
+                line: 4,
+                value: None
+            }]
+        );
+
+        assert_eq!(
+            Parser::new("fun f(a, 1) {}").parse().unwrap_err(),
+            [syntax(
+                Token::new(Type::Number, "1", Literal::Number(1.0)),
+                9,
+                1,
+                10,
+                SyntaxKind::ExpectParameterName
+            )]
+        );
+        assert_eq!(
+            Parser::new("fun f() print 1;").parse().unwrap_err()[0].to_string(),
+            "[line 1] Error at 'print': Expect '{' before function body."
+        );
+    }
+
+    #[test]
+    fn properties() {
+        assert_eq!(parse("a.b.c").unwrap(), "(. (. a b) c)");
+        assert_eq!(
+            parse("a.b(1).c = 2").unwrap(),
+            "(= (. (call (. a b) 1.0) c) 2.0)"
+        );
+        assert_eq!(parse("this.x").unwrap(), "(. this x)");
+        assert_eq!(parse("super.init()").unwrap(), "(call (. super init))");
+        assert_eq!(
+            parse("super").unwrap_err(),
+            [syntax(Token::EOF, 5, 1, 6, SyntaxKind::ExpectDotAfterSuper)]
+        );
+        assert_eq!(
+            parse("a.1").unwrap_err()[0].to_string(),
+            "[line 1] Error at '1': Expect property name after '.'."
+        );
+    }
+
+    #[test]
+    fn classes() {
+        let input = "\
+        class B < A {\n\
+            init(x) { this.x = x; }\n\
+            get() { return this.x; }\n\
+        }\n\
+        ";
+        let mut parser = Parser::new(input);
+        let stmts = parser.parse().unwrap();
+        let names = parser.names();
+        let [Stmt::Class(class)] = &stmts[..] else {
+            panic!("not a class: {stmts:?}");
+        };
+        assert_eq!(names.resolve(class.name), "B");
+        assert_eq!(
+            class.superclass,
+            Some(Expr::Variable {
+                name: names.get("A").unwrap(),
+                line: 1,
+                depth: None
+            })
+        );
+        assert_eq!(
+            class
+                .methods
+                .iter()
+                .map(|x| names.resolve(x.name))
+                .collect::<Vec<_>>(),
+            ["init", "get"]
+        );
+
+        assert_eq!(
+            Parser::new("class A { fun f() {} }").parse().unwrap_err()[0].to_string(),
+            "[line 1] Error at 'fun': Expect method name."
+        );
+        assert_eq!(
+            Parser::new("class A < {}").parse().unwrap_err()[0].to_string(),
+            "[line 1] Error at '{': Expect superclass name."
+        );
+    }
+
+    #[test]
+    fn error_display() {
+        let errors = parse("(72 +)").unwrap_err();
+        assert_eq!(
+            errors[0].to_string(),
+            "[line 1] Error at ')': Expect expression."
+        );
+        let errors = parse("(1").unwrap_err();
+        assert_eq!(
+            errors[0].to_string(),
+            "[line 1] Error at end: Expect ')' after expression."
+        );
+    }
+}
@@ -0,0 +1,236 @@
+use std::{borrow::Cow, ops::Range};
+
+use crate::{
+    scanner::{Error, LexicalKind},
+    Literal, Scanner, Span, Token,
+};
+
+/// What [`Scanner`] yields.
+pub type Scanned<'a> = Result<Token<'a>, Error>;
+
+/// Replacement of `range` of a source by `text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit<'e> {
+    pub range: Range<usize>,
+    pub text: &'e str,
+}
+
+impl Edit<'_> {
+    /// `source` with this edit applied.
+    #[must_use]
+    pub fn apply(&self, source: &str) -> String {
+        [
+            &source[..self.range.start],
+            self.text,
+            &source[self.range.end..],
+        ]
+        .concat()
+    }
+
+    /// Where byte `offset` of the old source, past the edit, ends up.
+    const fn shift(&self, offset: usize) -> usize {
+        offset - (self.range.end - self.range.start) + self.text.len()
+    }
+}
+
+/// Tokens of an edited source.
+#[derive(Debug)]
+pub struct Relexed<'a> {
+    pub tokens: Vec<Scanned<'a>>,
+    /// The part of `tokens` that was scanned again, the rest was carried over from before the
+    /// edit.
+    pub rescanned: Range<usize>,
+}
+
+/// Updates `old`, everything scanned from a source [with recovery](Scanner::with_recovery),
+/// after `edit` turned that source into `source`.
+///
+/// Scanning restarts at the end of a token before the edit and stops as soon as it produces a
+/// token that was also produced before the edit, at the same place in the unchanged text.
+#[must_use]
+pub fn relex<'a>(old: &[Scanned<'_>], edit: &Edit, source: &'a str) -> Relexed<'a> {
+    // Errors can end in the middle of a string, only tokens end between two tokens. The last
+    // token before the edit may also grow into it, as `1` does into `1.5`, so it's scanned again.
+    let before = old
+        .iter()
+        .enumerate()
+        .take_while(|(_, x)| span_of(x).end < edit.range.start)
+        .filter(|(_, x)| x.is_ok())
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    let keep = before.iter().nth_back(1).map_or(0, |i| i + 1);
+    let (offset, line) = keep.checked_sub(1).map_or((0, 1), |i| {
+        let span = span_of(&old[i]);
+        let text = &source[span.start..span.end];
+        (span.end, span.line + text.matches('\n').count())
+    });
+
+    let mut tokens = old[..keep]
+        .iter()
+        .map(|x| moved(x, source, span_of(x)))
+        .collect::<Vec<_>>();
+    let start = tokens.len();
+
+    let mut after = old
+        .iter()
+        .enumerate()
+        .skip_while(|(_, x)| span_of(x).start < edit.range.end)
+        .peekable();
+    for item in Scanner::resume(source, offset, line).with_recovery() {
+        let span = span_of(&item);
+        while after
+            .peek()
+            .is_some_and(|(_, x)| edit.shift(span_of(x).start) < span.start)
+        {
+            after.next();
+        }
+        match after.peek() {
+            Some(&(i, Ok(anchor)))
+                if item.is_ok() && edit.shift(anchor.span.start) == span.start =>
+            {
+                let rescanned = start..tokens.len();
+                let anchor = anchor.span;
+                tokens.extend(old[i..].iter().map(|x| {
+                    let old = span_of(x);
+                    let to = Span {
+                        start: edit.shift(old.start),
+                        end: edit.shift(old.end),
+                        line: old.line - anchor.line + span.line,
+                        column: if old.line == anchor.line {
+                            old.column - anchor.column + span.column
+                        } else {
+                            old.column
+                        },
+                    };
+                    moved(x, source, to)
+                }));
+                return Relexed { tokens, rescanned };
+            }
+            _ => tokens.push(item),
+        }
+    }
+    Relexed {
+        rescanned: start..tokens.len(),
+        tokens,
+    }
+}
+
+/// Where an item of a scan lies in the source.
+pub const fn span_of(item: &Scanned) -> Span {
+    match item {
+        Ok(token) => token.span,
+        Err(Error::Lexical { span, .. }) => *span,
+    }
+}
+
+/// `item` borrowing from `source` instead, where it spans `to`.
+fn moved<'a>(item: &Scanned, source: &'a str, to: Span) -> Scanned<'a> {
+    let token = match item {
+        Ok(token) => token,
+        Err(Error::Lexical { kind, .. }) => {
+            let kind = match kind {
+                // The line it names moves along with it.
+                LexicalKind::UnterminatedComment(_) => LexicalKind::UnterminatedComment(to.line),
+                kind => kind.clone(),
+            };
+            return Err(Error::lexical(to, kind));
+        }
+    };
+    let text = &source[to.start..to.end];
+    let lexeme = match &token.lexeme {
+        Cow::Borrowed(_) => Cow::Borrowed(text),
+        Cow::Owned(x) => Cow::Owned(x.clone()),
+    };
+    let literal = match &token.literal {
+        Literal::Null => Literal::Null,
+        Literal::Number(x) => Literal::Number(*x),
+        Literal::String(Cow::Borrowed(_)) => {
+            Literal::String(Cow::Borrowed(&text[1..text.len() - 1]))
+        }
+        Literal::String(Cow::Owned(x)) => Literal::String(Cow::Owned(x.clone())),
+    };
+    Ok(Token {
+        ty: token.ty,
+        lexeme,
+        literal,
+        span: to,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    /// Tokens along with their spans, which they don't compare by.
+    fn spanned<'a>(items: &[Scanned<'a>]) -> Vec<(Scanned<'a>, Span)> {
+        items.iter().map(|x| (x.clone(), span_of(x))).collect()
+    }
+
+    fn check(source: &str, edit: &Edit) -> Range<usize> {
+        let old = Scanner::new(source).with_recovery().collect::<Vec<_>>();
+        let source = edit.apply(source);
+        let relexed = relex(&old, edit, &source);
+        let expected = Scanner::new(&source).with_recovery().collect::<Vec<_>>();
+        assert_eq!(spanned(&relexed.tokens), spanned(&expected), "{source:?}");
+        relexed.rescanned
+    }
+
+    const fn edit(range: Range<usize>, text: &str) -> Edit<'_> {
+        Edit { range, text }
+    }
+
+    #[test]
+    fn rescans_locally() {
+        let source = "var a = 1;\nvar b = 2;\nvar c = 3;\nprint a + b + c;\n";
+        assert_eq!(check(source, &edit(19..20, "42")), 7..9);
+        assert_eq!(check(source, &edit(15..16, "bee")), 5..7);
+        assert_eq!(check(source, &edit(10..11, "")), 3..5);
+        assert_eq!(check(source, &edit(0..0, "\n\n")), 0..0);
+    }
+
+    #[test]
+    fn matches_full_scan() {
+        let source = "\
+            var s = \"a\\tb\"; // comment\n\
+            /* block */ print 1.5 + s;\n\
+            fun f(x) { return x >= 0x10; }\n\
+            print caf\u{e9} @ \"multi\nline\";\n\
+        ";
+        let edits = [
+            edit(0..0, "print 0;"),
+            edit(3..4, "ar \"open"),
+            edit(9..9, "\\q"),
+            edit(16..18, "/*"),
+            edit(46..47, "0."),
+            edit(47..48, "e"),
+            edit(74..76, "<"),
+            edit(77..79, "0b1_"),
+            edit(91..96, "\n\n\n"),
+            edit(105..106, "\""),
+            edit(source.len()..source.len(), "\""),
+            edit(0..source.len(), ""),
+        ];
+        for edit in &edits {
+            assert!(source.is_char_boundary(edit.range.start));
+            assert!(source.is_char_boundary(edit.range.end));
+            check(source, edit);
+        }
+        for i in 0..=source.len() {
+            if source.is_char_boundary(i) {
+                check(source, &edit(i..i, "\""));
+                check(source, &edit(i..i, "1"));
+                check(source, &edit(i..i, "/*"));
+                check(source, &edit(i..i, "\n"));
+            }
+        }
+
+        // Errors carried over from before the edit.
+        let open = "a\n/* x";
+        for i in 0..=open.len() {
+            check(open, &edit(i..i, "\n"));
+            check(open, &edit(i..i, "b "));
+        }
+    }
+}
@@ -0,0 +1,442 @@
+use std::{collections::HashMap, rc::Rc};
+
+use thiserror::Error;
+
+use crate::{
+    ast::{Class, Function},
+    parser::Location,
+    Diagnostic, Expr, Interner, Stmt, Symbol,
+};
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum Error {
+    #[error("[line {line}] Error{at}: {kind}")]
+    Semantic {
+        line: usize,
+        at: Location,
+        kind: SemanticKind,
+    },
+}
+
+impl Error {
+    #[inline]
+    pub(crate) fn semantic(line: usize, lexeme: &str, kind: SemanticKind) -> Self {
+        Self::Semantic {
+            line,
+            at: Location::Lexeme(lexeme.to_owned()),
+            kind,
+        }
+    }
+}
+
+impl Diagnostic for Error {
+    fn line(&self) -> usize {
+        match self {
+            Self::Semantic { line, .. } => *line,
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            Self::Semantic { kind, .. } => kind.to_string(),
+        }
+    }
+
+    fn code(&self) -> Option<&'static str> {
+        match self {
+            Self::Semantic { kind, .. } => Some(kind.code()),
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SemanticKind {
+    #[error("Can't read local variable in its own initializer.")]
+    ReadInOwnInitializer,
+    #[error("Already a variable with this name in this scope.")]
+    AlreadyDeclared,
+    #[error("Can't return from top-level code.")]
+    TopLevelReturn,
+    #[error("Can't return a value from an initializer.")]
+    ReturnFromInitializer,
+    #[error("Can't use 'this' outside of a class.")]
+    ThisOutsideClass,
+    #[error("Can't use 'super' outside of a class.")]
+    SuperOutsideClass,
+    #[error("Can't use 'super' in a class with no superclass.")]
+    SuperWithoutSuperclass,
+    #[error("A class can't inherit from itself.")]
+    InheritFromSelf,
+}
+
+impl SemanticKind {
+    /// Stable code of this kind of error, never reused for another one.
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::ReadInOwnInitializer => "S0001",
+            Self::AlreadyDeclared => "S0002",
+            Self::TopLevelReturn => "S0003",
+            Self::ReturnFromInitializer => "S0004",
+            Self::ThisOutsideClass => "S0005",
+            Self::SuperOutsideClass => "S0006",
+            Self::SuperWithoutSuperclass => "S0007",
+            Self::InheritFromSelf => "S0008",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FunctionType {
+    None,
+    Function,
+    Initializer,
+    Method,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+/// Binds every variable use to its declaration ahead of execution.
+pub struct Resolver<'n> {
+    /// Each local scope maps names to whether their initializer has been resolved yet.
+    scopes: Vec<HashMap<Symbol, bool>>,
+    /// The [`Parser`](crate::Parser)'s names, which the syntax tree refers to.
+    names: &'n Interner,
+    this: Symbol,
+    super_: Symbol,
+    init: Symbol,
+    function: FunctionType,
+    class: ClassType,
+    errors: Vec<Error>,
+}
+
+impl<'n> Resolver<'n> {
+    #[must_use]
+    pub fn new(names: &'n mut Interner) -> Self {
+        Self {
+            scopes: Vec::new(),
+            this: names.intern("this"),
+            super_: names.intern("super"),
+            init: names.intern("init"),
+            names,
+            function: FunctionType::None,
+            class: ClassType::None,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Fills in the scope depth of every variable in `statements`.
+    pub fn resolve(&mut self, statements: &mut [Stmt]) -> Result<(), Vec<Error>> {
+        self.statements(statements);
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    fn statements(&mut self, statements: &mut [Stmt]) {
+        for stmt in statements {
+            self.statement(stmt);
+        }
+    }
+
+    fn statement(&mut self, stmt: &mut Stmt) {
+        match stmt {
+            Stmt::Expression(expr) | Stmt::Print(expr) => self.expression(expr),
+            Stmt::Var {
+                name,
+                line,
+                initializer,
+            } => {
+                self.declare(*name, *line);
+                if let Some(initializer) = initializer {
+                    self.expression(initializer);
+                }
+                self.define(*name);
+            }
+            Stmt::Block(statements) => {
+                self.scopes.push(HashMap::new());
+                self.statements(statements);
+                self.scopes.pop();
+            }
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.expression(condition);
+                self.statement(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.statement(else_branch);
+                }
+            }
+            Stmt::While { condition, body } => {
+                self.expression(condition);
+                self.statement(body);
+            }
+            Stmt::Function(function) => {
+                let function = unshared(function);
+                self.declare(function.name, function.line);
+                self.define(function.name);
+                self.function(function, FunctionType::Function);
+            }
+            Stmt::Class(class) => self.class(class),
+            Stmt::Return { line, value } => {
+                if self.function == FunctionType::None {
+                    self.error(*line, "return", SemanticKind::TopLevelReturn);
+                }
+                if let Some(value) = value {
+                    if self.function == FunctionType::Initializer {
+                        self.error(*line, "return", SemanticKind::ReturnFromInitializer);
+                    }
+                    self.expression(value);
+                }
+            }
+        }
+    }
+
+    fn function(&mut self, function: &mut Function, ty: FunctionType) {
+        let enclosing = std::mem::replace(&mut self.function, ty);
+        self.scopes.push(HashMap::new());
+        for &(param, line) in &function.params {
+            self.declare(param, line);
+            self.define(param);
+        }
+        self.statements(&mut function.body);
+        self.scopes.pop();
+        self.function = enclosing;
+    }
+
+    fn class(&mut self, class: &mut Class) {
+        let enclosing = std::mem::replace(&mut self.class, ClassType::Class);
+        self.declare(class.name, class.line);
+        self.define(class.name);
+
+        if let Some(superclass) = &mut class.superclass {
+            if let Expr::Variable { name, line, .. } = superclass {
+                if *name == class.name {
+                    let names = self.names;
+                    self.error(*line, names.resolve(*name), SemanticKind::InheritFromSelf);
+                }
+            }
+            self.class = ClassType::Subclass;
+            self.expression(superclass);
+            self.scopes.push(HashMap::from([(self.super_, true)]));
+        }
+
+        self.scopes.push(HashMap::from([(self.this, true)]));
+        for method in &mut class.methods {
+            let method = unshared(method);
+            let ty = if method.name == self.init {
+                FunctionType::Initializer
+            } else {
+                FunctionType::Method
+            };
+            self.function(method, ty);
+        }
+        self.scopes.pop();
+
+        if class.superclass.is_some() {
+            self.scopes.pop();
+        }
+        self.class = enclosing;
+    }
+
+    fn expression(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Nil | Expr::Bool(_) | Expr::Literal(_) => (),
+            Expr::Grouping(x) | Expr::Unary { right: x, .. } => self.expression(x),
+            Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+                self.expression(left);
+                self.expression(right);
+            }
+            Expr::Variable { name, line, depth } => {
+                if self
+                    .scopes
+                    .last()
+                    .is_some_and(|scope| scope.get(name) == Some(&false))
+                {
+                    let names = self.names;
+                    self.error(
+                        *line,
+                        names.resolve(*name),
+                        SemanticKind::ReadInOwnInitializer,
+                    );
+                }
+                *depth = self.local(*name);
+            }
+            Expr::Assign {
+                name, depth, value, ..
+            } => {
+                self.expression(value);
+                *depth = self.local(*name);
+            }
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                self.expression(callee);
+                for argument in arguments {
+                    self.expression(argument);
+                }
+            }
+            Expr::Get { object, .. } => self.expression(object),
+            Expr::Set { object, value, .. } => {
+                self.expression(value);
+                self.expression(object);
+            }
+            Expr::This { line, depth } => {
+                if self.class == ClassType::None {
+                    self.error(*line, "this", SemanticKind::ThisOutsideClass);
+                    return;
+                }
+                *depth = self.local(self.this);
+            }
+            Expr::Super { line, depth, .. } => {
+                match self.class {
+                    ClassType::None => {
+                        self.error(*line, "super", SemanticKind::SuperOutsideClass);
+                    }
+                    ClassType::Class => {
+                        self.error(*line, "super", SemanticKind::SuperWithoutSuperclass);
+                    }
+                    ClassType::Subclass => (),
+                }
+                *depth = self.local(self.super_);
+            }
+        }
+    }
+
+    fn declare(&mut self, name: Symbol, line: usize) {
+        let Some(scope) = self.scopes.last_mut() else {
+            return;
+        };
+        if let Some(defined) = scope.get_mut(&name) {
+            *defined = false;
+            let names = self.names;
+            self.error(line, names.resolve(name), SemanticKind::AlreadyDeclared);
+        } else {
+            scope.insert(name, false);
+        }
+    }
+
+    fn define(&mut self, name: Symbol) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name, true);
+        }
+    }
+
+    /// Number of scopes to walk up to find `name`, `None` if it must be a global.
+    fn local(&self, name: Symbol) -> Option<usize> {
+        self.scopes
+            .iter()
+            .rev()
+            .position(|scope| scope.contains_key(&name))
+    }
+
+    fn error(&mut self, line: usize, lexeme: &str, kind: SemanticKind) {
+        self.errors.push(Error::semantic(line, lexeme, kind));
+    }
+}
+
+/// Functions are only shared once the interpreter creates closures out of them.
+fn unshared<'a, 'b>(function: &'b mut Rc<Function<'a>>) -> &'b mut Function<'a> {
+    Rc::get_mut(function).expect("functions are resolved before being executed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Parser;
+    use pretty_assertions::assert_eq;
+
+    fn resolve(input: &str) -> Result<(Vec<Stmt<'_>>, Interner), Vec<String>> {
+        let mut parser = Parser::new(input);
+        let mut stmts = parser.parse().unwrap();
+        let mut names = parser.into_names();
+        Resolver::new(&mut names)
+            .resolve(&mut stmts)
+            .map_err(|errors| errors.iter().map(ToString::to_string).collect::<Vec<_>>())?;
+        Ok((stmts, names))
+    }
+
+    #[test]
+    fn depths() {
+        let (stmts, names) = resolve("var a; { var b; { a; b; } }").unwrap();
+        let Stmt::Block(outer) = &stmts[1] else {
+            panic!("not a block: {:?}", stmts[1]);
+        };
+        let Stmt::Block(inner) = &outer[1] else {
+            panic!("not a block: {:?}", outer[1]);
+        };
+        assert!(matches!(
+            &inner[..],
+            [
+                Stmt::Expression(Expr::Variable {
+                    name: a,
+                    depth: None,
+                    ..
+                }),
+                Stmt::Expression(Expr::Variable {
+                    name: b,
+                    depth: Some(1),
+                    ..
+                }),
+            ] if names.resolve(*a) == "a" && names.resolve(*b) == "b"
+        ));
+    }
+
+    #[test]
+    fn locals() {
+        assert_eq!(
+            resolve("{\nvar a = a;\n}").unwrap_err(),
+            ["[line 2] Error at 'a': Can't read local variable in its own initializer."]
+        );
+        assert_eq!(
+            resolve("{\nvar a;\nvar a;\n}").unwrap_err(),
+            ["[line 3] Error at 'a': Already a variable with this name in this scope."]
+        );
+        assert_eq!(
+            resolve("fun f(a,\nb, a) {}").unwrap_err(),
+            ["[line 2] Error at 'a': Already a variable with this name in this scope."]
+        );
+        assert!(resolve("var a = 1; var a = a;").is_ok());
+    }
+
+    #[test]
+    fn returns() {
+        assert_eq!(
+            resolve("return 1;").unwrap_err(),
+            ["[line 1] Error at 'return': Can't return from top-level code."]
+        );
+        assert_eq!(
+            resolve("class A { init() {\nreturn 1;\n} }").unwrap_err(),
+            ["[line 2] Error at 'return': Can't return a value from an initializer."]
+        );
+        assert!(resolve("class A { init() { return; } }").is_ok());
+    }
+
+    #[test]
+    fn classes() {
+        assert_eq!(
+            resolve("print this;").unwrap_err(),
+            ["[line 1] Error at 'this': Can't use 'this' outside of a class."]
+        );
+        assert_eq!(
+            resolve("fun f() { super.f(); }").unwrap_err(),
+            ["[line 1] Error at 'super': Can't use 'super' outside of a class."]
+        );
+        assert_eq!(
+            resolve("class A { f() { super.f(); } }").unwrap_err(),
+            ["[line 1] Error at 'super': Can't use 'super' in a class with no superclass."]
+        );
+        assert_eq!(
+            resolve("class A < A {}").unwrap_err(),
+            ["[line 1] Error at 'A': A class can't inherit from itself."]
+        );
+    }
+}
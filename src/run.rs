@@ -0,0 +1,400 @@
+use std::io::{self, Write};
+
+use crate::{interpreter, vm, Diagnostic, Interpreter, Parser, Renderer, Resolver, Style, Vm};
+
+/// How programs are executed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// Interprets the syntax tree directly.
+    #[default]
+    TreeWalk,
+    /// Compiles to bytecode for a virtual machine.
+    Vm,
+}
+
+pub struct Runner<'a, O, E> {
+    input: &'a str,
+    out: &'a mut O,
+    err: &'a mut E,
+    diagnostics: Renderer<'a>,
+    backend: Backend,
+    gc_stress: bool,
+    pub code: i32,
+}
+
+impl<'a, O, E> Runner<'a, O, E>
+where
+    O: Write,
+    E: Write,
+{
+    pub fn new(input: &'a str, out: &'a mut O, err: &'a mut E) -> Self {
+        Self {
+            input,
+            out,
+            err,
+            diagnostics: Renderer::new(input),
+            backend: Backend::default(),
+            gc_stress: false,
+            code: 0,
+        }
+    }
+
+    /// Reports errors in `style`, naming `file` as the origin of the input.
+    #[must_use]
+    pub const fn with_diagnostics(mut self, style: Style, file: &'a str) -> Self {
+        self.diagnostics = self.diagnostics.with_style(style, file);
+        self
+    }
+
+    #[must_use]
+    pub const fn with_backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Makes the virtual machine collect garbage before every allocation.
+    #[must_use]
+    pub const fn with_gc_stress(mut self) -> Self {
+        self.gc_stress = true;
+        self
+    }
+
+    pub fn run(&mut self) -> io::Result<()> {
+        let result = match self.backend {
+            Backend::TreeWalk => {
+                let mut parser = Parser::new(self.input);
+                let mut statements = match parser.parse() {
+                    Ok(statements) => statements,
+                    Err(errors) => return self.compile_errors(errors),
+                };
+                let mut names = parser.into_names();
+                if let Err(errors) = Resolver::new(&mut names).resolve(&mut statements) {
+                    return self.compile_errors(errors);
+                }
+                Interpreter::new(&mut *self.out, names).interpret(&statements)
+            }
+            Backend::Vm => {
+                let mut vm = Vm::new(&mut *self.out);
+                if self.gc_stress {
+                    vm = vm.with_gc_stress();
+                }
+                match vm.compile(self.input) {
+                    Ok(function) => vm.interpret(function),
+                    Err(vm::Errors::Syntax(errors)) => return self.compile_errors(errors),
+                    Err(vm::Errors::Semantic(errors)) => return self.compile_errors(errors),
+                }
+            }
+        };
+
+        match result {
+            Ok(()) => (),
+            Err(interpreter::Error::Io(e)) => return Err(e),
+            Err(e) => {
+                self.code = 70;
+                self.diagnostics.render(self.err, &e)?;
+            }
+        }
+
+        self.err.flush()?;
+        Ok(())
+    }
+
+    fn compile_errors<T: Diagnostic>(&mut self, errors: Vec<T>) -> io::Result<()> {
+        self.code = 65;
+        for e in errors {
+            self.diagnostics.render(self.err, &e)?;
+        }
+        self.err.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+
+    use pretty_assertions::assert_eq;
+
+    fn run_with(input: &str, backend: Backend) -> (String, String, i32) {
+        run_stressed(input, backend, false)
+    }
+
+    fn run_stressed(input: &str, backend: Backend, gc_stress: bool) -> (String, String, i32) {
+        let mut out_buf = Vec::new();
+        let mut err_buf = Vec::new();
+        let mut runner = Runner::new(input, &mut out_buf, &mut err_buf).with_backend(backend);
+        if gc_stress {
+            runner = runner.with_gc_stress();
+        }
+        runner.run().unwrap();
+        let code = runner.code;
+        (
+            String::from_utf8(out_buf).unwrap(),
+            String::from_utf8(err_buf).unwrap(),
+            code,
+        )
+    }
+
+    /// Runs `input` on every backend, and on the virtual machine collecting garbage as often as
+    /// it can, which must all agree.
+    fn run(input: &str) -> (String, String, i32) {
+        let result = run_with(input, Backend::TreeWalk);
+        assert_eq!(run_with(input, Backend::Vm), result, "{input}");
+        let stressed = run_stressed(input, Backend::Vm, true);
+        assert_eq!(stressed, result, "{input}");
+        result
+    }
+
+    #[test]
+    fn out() {
+        let input = "\
+        var greeting = \"Hello\";\n\
+        print greeting + \", world!\";\n\
+        ";
+        assert_eq!(run(input), ("Hello, world!\n".into(), String::new(), 0));
+    }
+
+    #[test]
+    fn unicode_names() {
+        let input =
+            "var caf\u{e9} = 1; { var cafe\u{301} = 2; print caf\u{e9}; } print cafe\u{301};";
+        assert_eq!(run(input), ("2\n1\n".into(), String::new(), 0));
+    }
+
+    #[test]
+    fn rich_runtime_err() {
+        let mut err = Vec::new();
+        Runner::new("print 1;\nprint -true;", &mut io::sink(), &mut err)
+            .with_diagnostics(Style::Rich, "test.lox")
+            .run()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(err).unwrap(),
+            "\
+error[R0001]: Operand must be a number.
+ --> test.lox:2
+  |
+2 | print -true;
+  |
+  = note: run `explain R0001` for more about this error
+"
+        );
+    }
+
+    #[test]
+    fn runtime_err() {
+        let input = "\
+        print \"before\";\n\
+        print -true;\n\
+        print \"after\";\n\
+        ";
+        assert_eq!(
+            run(input),
+            (
+                "before\n".into(),
+                "Operand must be a number.\n[line 2]\n".into(),
+                70
+            )
+        );
+    }
+
+    #[test]
+    fn resolve_err() {
+        let input = "\
+        print \"never\";\n\
+        fun f() {\n\
+            var a = 1;\n\
+            var a = 2;\n\
+        }\n\
+        return;\n\
+        ";
+        assert_eq!(
+            run(input),
+            (
+                String::new(),
+                "\
+                [line 4] Error at 'a': Already a variable with this name in this scope.\n\
+                [line 6] Error at 'return': Can't return from top-level code.\n\
+                "
+                .into(),
+                65
+            )
+        );
+    }
+
+    #[test]
+    fn syntax_err() {
+        let input = "\
+        print \"never\";\n\
+        var 1 = 2;\n\
+        print;\n\
+        ";
+        assert_eq!(
+            run(input),
+            (
+                String::new(),
+                "\
+                [line 2] Error at '1': Expect variable name.\n\
+                [line 3] Error at ';': Expect expression.\n\
+                "
+                .into(),
+                65
+            )
+        );
+    }
+
+    #[test]
+    fn classes() {
+        let input = "\
+        class A {\n\
+            init(x) { this.x = x; }\n\
+            get() { return this.x; }\n\
+            name() { return \"A\"; }\n\
+        }\n\
+        class B < A {\n\
+            init(x) { super.init(x * 2); }\n\
+            name() { return \"B<\" + super.name(); }\n\
+        }\n\
+        var b = B(5);\n\
+        print b.get();\n\
+        print b.name();\n\
+        print b;\n\
+        print B;\n\
+        print b.get;\n\
+        print b.get == b.get;\n\
+        print b.init(1) == b;\n\
+        b.y = b.x = 3;\n\
+        print b.x + b.y;\n\
+        ";
+        assert_eq!(
+            run(input),
+            (
+                "10\nB<A\nB instance\nB\n<fn get>\nfalse\ntrue\n6\n".into(),
+                String::new(),
+                0
+            )
+        );
+    }
+
+    #[test]
+    fn closures() {
+        let input = "\
+        fun counter() {\n\
+            var n = 0;\n\
+            fun count() { n = n + 1; return n; }\n\
+            return count;\n\
+        }\n\
+        var c = counter();\n\
+        c();\n\
+        print c();\n\
+        print counter()();\n\
+        var f;\n\
+        { var a = \"block\"; fun g() { return a; } f = g; }\n\
+        { var b = \"other\"; print f(); }\n\
+        print clock;\n\
+        print counter;\n\
+        ";
+        assert_eq!(
+            run(input),
+            (
+                "2\n1\nblock\n<native fn>\n<fn counter>\n".into(),
+                String::new(),
+                0
+            )
+        );
+    }
+
+    #[test]
+    fn runtime_err_lines() {
+        let cases = [
+            (
+                "var a = 1;\nprint a +\n\"b\";",
+                2,
+                "Operands must be two numbers or two strings.",
+            ),
+            (
+                "fun f(a) {}\nf(\n1,\n2);",
+                4,
+                "Expected 1 arguments but got 2.",
+            ),
+            ("class A {}\nA()\n.x;", 3, "Undefined property 'x'."),
+            ("nil\n.x = 1;", 2, "Only instances have fields."),
+            (
+                "var a = 1;\nclass B <\na {}",
+                3,
+                "Superclass must be a class.",
+            ),
+            ("x\n= 1;", 1, "Undefined variable 'x'."),
+            ("\"f\"();", 1, "Can only call functions and classes."),
+        ];
+        for (input, line, message) in cases {
+            assert_eq!(
+                run(input),
+                (String::new(), format!("{message}\n[line {line}]\n"), 70)
+            );
+        }
+    }
+
+    #[test]
+    fn resolve_err_order() {
+        let input = "\
+        class A { m() { return super.x; } }\n\
+        { var a = 1; var a = a; }\n\
+        for (var i = 0; i < 1; i = this) { var y = y; }\n\
+        { var o; o.p = this; var q = q = 1; }\n\
+        class B < B {}\n\
+        ";
+        assert_eq!(
+            run(input),
+            (
+                String::new(),
+                "\
+                [line 1] Error at 'super': Can't use 'super' in a class with no superclass.\n\
+                [line 2] Error at 'a': Already a variable with this name in this scope.\n\
+                [line 2] Error at 'a': Can't read local variable in its own initializer.\n\
+                [line 3] Error at 'y': Can't read local variable in its own initializer.\n\
+                [line 3] Error at 'this': Can't use 'this' outside of a class.\n\
+                [line 4] Error at 'this': Can't use 'this' outside of a class.\n\
+                [line 5] Error at 'B': A class can't inherit from itself.\n\
+                "
+                .into(),
+                65
+            )
+        );
+    }
+
+    /// Runs `input` like [`run`], on a thread with room for [`vm::FRAMES_MAX`] calls of the
+    /// tree-walker, which recurses natively for each of them.
+    fn run_deep(input: &str) -> (String, String, i32) {
+        thread::scope(|scope| {
+            thread::Builder::new()
+                .stack_size(1 << 28)
+                .spawn_scoped(scope, || run(input))
+                .unwrap()
+                .join()
+                .unwrap()
+        })
+    }
+
+    #[test]
+    fn stack_overflow() {
+        let input = "fun f() { f(); }\nf();";
+        assert_eq!(
+            run_deep(input),
+            (String::new(), "Stack overflow.\n[line 1]\n".into(), 70)
+        );
+
+        // The deepest calls that fit, the top-level code taking up a frame.
+        let input = "fun f(n) {\n  if (n == 0) return 0;\n  return 1 + f(n - 1);\n}\n";
+        let deepest = vm::FRAMES_MAX - 2;
+        assert_eq!(
+            run_deep(&format!("{input}print f({deepest});")),
+            (format!("{deepest}\n"), String::new(), 0)
+        );
+        assert_eq!(
+            run_deep(&format!("{input}print f({});", deepest + 1)),
+            (String::new(), "Stack overflow.\n[line 3]\n".into(), 70)
+        );
+    }
+}
@@ -1,103 +1,509 @@
-use std::{iter::Peekable, str::CharIndices};
+use std::{borrow::Cow, iter::Peekable, str::CharIndices};
 
 use itertools::{Itertools, PeekingNext};
+use serde::Serialize;
 use thiserror::Error;
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+use unicode_xid::UnicodeXID;
 
-use crate::{Literal, Token, Type};
+use crate::{Diagnostic, Literal, Lossless, Span, Token, Type};
 
-#[derive(Debug, Error, PartialEq, Eq)]
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
 pub enum Error {
-    #[error("[line {line}] Error: {kind}")]
-    Lexical { line: usize, kind: LexicalKind },
+    #[error("[line {}] Error: {kind}", span.line)]
+    Lexical { span: Span, kind: LexicalKind },
 }
 
 impl Error {
     #[inline]
-    pub(crate) const fn lexical(line: usize, kind: LexicalKind) -> Self {
-        Self::Lexical { line, kind }
+    pub(crate) const fn lexical(span: Span, kind: LexicalKind) -> Self {
+        Self::Lexical { span, kind }
     }
 }
 
-#[derive(Debug, Error, PartialEq, Eq)]
+impl Diagnostic for Error {
+    fn line(&self) -> usize {
+        match self {
+            Self::Lexical { span, .. } => span.line,
+        }
+    }
+
+    fn span(&self) -> Option<Span> {
+        match self {
+            Self::Lexical { span, .. } => Some(*span),
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            Self::Lexical { kind, .. } => kind.to_string(),
+        }
+    }
+
+    fn code(&self) -> Option<&'static str> {
+        match self {
+            Self::Lexical { kind, .. } => Some(kind.code()),
+        }
+    }
+
+    fn help(&self) -> Option<&'static str> {
+        match self {
+            Self::Lexical { kind, .. } => kind.help(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Error, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", content = "value")]
 pub enum LexicalKind {
     #[error("Unexpected character: {0}")]
     UnexpectedCharacter(char),
+    /// Several unexpected characters in a row.
+    #[error("Unexpected characters: {0}")]
+    UnexpectedSequence(String),
     #[error("Unterminated string.")]
     UnterminatedString,
+    #[error("Invalid escape sequence: {}", escaped(.0))]
+    InvalidEscape(String),
+    #[error("Unterminated block comment starting on line {0}.")]
+    UnterminatedComment(usize),
+    #[error("Malformed number: {0}.")]
+    MalformedNumber(NumberKind),
+}
+
+#[derive(Debug, Clone, Error, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", content = "value")]
+pub enum NumberKind {
+    #[error("expected digits after `0{0}`")]
+    MissingDigits(char),
+    #[error("expected digits in the exponent")]
+    MissingExponent,
+    #[error("`_` must be followed by a digit")]
+    TrailingSeparator,
+    #[error("`{digit}` is not a base {radix} digit")]
+    InvalidDigit { digit: char, radix: u32 },
+}
+
+impl LexicalKind {
+    /// Stable code of this kind of error, never reused for another one.
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::UnexpectedCharacter(_) => "L0001",
+            Self::UnterminatedString => "L0002",
+            Self::InvalidEscape(_) => "L0003",
+            Self::UnterminatedComment(_) => "L0004",
+            Self::MalformedNumber(_) => "L0005",
+            Self::UnexpectedSequence(_) => "L0006",
+        }
+    }
+
+    const fn help(&self) -> Option<&'static str> {
+        match self {
+            Self::UnterminatedString => Some("strings must be closed with `\"`"),
+            Self::UnterminatedComment(_) => Some("block comments must be closed with `*/`"),
+            Self::MalformedNumber(NumberKind::TrailingSeparator) => {
+                Some("`_` can only separate digits, as in `1_000`")
+            }
+            Self::InvalidEscape(_) => {
+                Some(r#"valid escapes are `\n`, `\t`, `\r`, `\"`, `\\` and `\u{XXXX}`"#)
+            }
+            Self::UnexpectedCharacter(_)
+            | Self::UnexpectedSequence(_)
+            | Self::MalformedNumber(_) => None,
+        }
+    }
+}
+
+/// The escape sequence `sequence` with what follows its backslash escaped, so that a backslash
+/// before a line break stays on one line.
+fn escaped(sequence: &str) -> String {
+    let rest = sequence.strip_prefix('\\').unwrap_or(sequence);
+    format!("\\{}", rest.escape_debug())
+}
+
+/// Like [`CharIndices`], but over a suffix of the input with offsets into all of it.
+struct Chars<'a> {
+    inner: CharIndices<'a>,
+    offset: usize,
+}
+
+impl Iterator for Chars<'_> {
+    type Item = (usize, char);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(i, c)| (self.offset + i, c))
+    }
 }
 
 pub struct Scanner<'a> {
     raw: &'a str,
-    chars: Peekable<CharIndices<'a>>,
+    chars: Peekable<Chars<'a>>,
     eof: bool,
     line: usize,
+    /// Byte offset at which `line` starts.
+    line_start: usize,
+    /// `line` and `line_start` as they were at the start of the current token.
+    token_line: usize,
+    token_line_start: usize,
+    /// Byte offset and column of the start of the last span, so that computing the next column
+    /// on the same line only counts the characters in between.
+    column: (usize, usize),
+    /// Whether to report runs of unexpected characters as one error and to stop unterminated
+    /// strings at the end of their line, see [`Scanner::with_recovery`].
+    recovery: bool,
 }
 
 impl<'a> Scanner<'a> {
     #[must_use]
     pub fn new(input: &'a str) -> Self {
+        Self::resume(input, 0, 1)
+    }
+
+    /// Starts scanning at byte `offset` of `input`, which must lie between two tokens, and on
+    /// line `line`.
+    pub(crate) fn resume(input: &'a str, offset: usize, line: usize) -> Self {
+        let line_start = input[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let chars = Chars {
+            inner: input[offset..].char_indices(),
+            offset,
+        };
         Self {
             raw: input, // FIXME no Chars::as_str() for peekable :( https://github.com/rust-lang/rust/issues/33881
-            chars: input.char_indices().peekable(),
+            chars: chars.peekable(),
             eof: false,
-            line: 1,
+            line,
+            line_start,
+            token_line: line,
+            token_line_start: line_start,
+            column: (line_start, 1),
+            recovery: false,
+        }
+    }
+
+    /// Reports a run of unexpected characters as a single [`LexicalKind::UnexpectedSequence`],
+    /// and ends an unterminated string at the end of its line so that the lines after it are
+    /// still scanned as code. Meant for editors and reports listing every error at once.
+    #[must_use]
+    pub const fn with_recovery(mut self) -> Self {
+        self.recovery = true;
+        self
+    }
+
+    /// Switches to a mode that also yields the whitespace and comments around every token,
+    /// [recovering](Self::with_recovery) from errors.
+    #[must_use]
+    pub const fn lossless(self) -> Lossless<'a> {
+        Lossless::new(self.raw, self.with_recovery())
+    }
+
+    #[inline]
+    fn newline(&mut self, i: usize) {
+        self.line += 1;
+        self.line_start = i + 1;
+    }
+
+    /// Span from byte `start` to byte `end` of the current token.
+    fn span(&mut self, start: usize, end: usize) -> Span {
+        self.span_on(self.token_line, self.token_line_start, start, end)
+    }
+
+    /// Span from byte `start` to byte `end` of `line`, which starts at byte `line_start`.
+    fn span_on(&mut self, line: usize, line_start: usize, start: usize, end: usize) -> Span {
+        let (offset, column) = match self.column {
+            (offset, column) if (line_start..=start).contains(&offset) => (offset, column),
+            _ => (line_start, 1),
+        };
+        let column = column + self.raw[offset..start].chars().count();
+        self.column = (start, column);
+        Span {
+            start,
+            end,
+            line,
+            column,
         }
     }
 
+    /// Scans a string literal, borrowing its contents unless they contain escapes.
     fn string(&mut self, start: usize) -> Result<Token<'a>, Error> {
-        let start_line = self.line;
-        let end = self
-            .chars
-            .by_ref()
-            .inspect(|(_, c)| {
-                if *c == '\n' {
-                    self.line += 1;
+        let mut value: Option<String> = None;
+        let mut invalid = None;
+        while let Some((i, c)) = self.chars.next() {
+            match c {
+                '"' => {
+                    if let Some(e) = invalid {
+                        return Err(e);
+                    }
+                    let lexeme = &self.raw[start..=i];
+                    let literal = match value {
+                        Some(value) => Cow::Owned(value),
+                        None => Cow::Borrowed(&self.raw[start + 1..i]),
+                    };
+                    return Ok(Token::new(Type::String, lexeme, Literal::String(literal)));
                 }
-            })
-            .position(|(_, c)| c == '"')
-            .map(|pos| start + pos + 1);
+                '\\' => {
+                    let value = value.get_or_insert_with(|| self.raw[start + 1..i].to_owned());
+                    match self.escape(i) {
+                        Ok(Some(c)) => value.push(c),
+                        Ok(None) => break,
+                        Err(e) => {
+                            invalid.get_or_insert(e);
+                        }
+                    }
+                }
+                c => {
+                    if c == '\n' {
+                        self.newline(i);
+                    }
+                    if let Some(value) = &mut value {
+                        value.push(c);
+                    }
+                }
+            }
+        }
+        if !self.recovery {
+            return Err(Error::lexical(
+                self.span(start, self.raw.len()),
+                LexicalKind::UnterminatedString,
+            ));
+        }
+        // Nothing closes it, so it most likely lacks a quote on its first line, and the lines
+        // after it are code rather than part of the string.
+        let end = self.raw[start..]
+            .find(['\r', '\n'])
+            .map_or(self.raw.len(), |i| start + i);
+        self.seek(end, self.token_line, self.token_line_start);
+        Err(Error::lexical(
+            self.span(start, end),
+            LexicalKind::UnterminatedString,
+        ))
+    }
+
+    /// Continues scanning at byte `offset`, on `line`, which starts at byte `line_start`.
+    fn seek(&mut self, offset: usize, line: usize, line_start: usize) {
+        self.chars = Chars {
+            inner: self.raw[offset..].char_indices(),
+            offset,
+        }
+        .peekable();
+        (self.line, self.line_start) = (line, line_start);
+    }
 
-        let Some(end) = end else {
-            return Err(Error::lexical(start_line, LexicalKind::UnterminatedString));
+    /// Scans the unexpected character `c` at byte `start`, along with any unexpected characters
+    /// right after it when recovering.
+    fn unexpected(&mut self, start: usize, c: char) -> Error {
+        if !self.recovery {
+            return Error::lexical(
+                self.span(start, start + c.len_utf8()),
+                LexicalKind::UnexpectedCharacter(c),
+            );
+        }
+        let rest = self
+            .chars
+            .peeking_take_while(|&(_, c)| !starts_token(c))
+            .map(|(_, c)| c)
+            .collect::<String>();
+        let end = self.offset();
+        let kind = if rest.is_empty() {
+            LexicalKind::UnexpectedCharacter(c)
+        } else {
+            LexicalKind::UnexpectedSequence(format!("{c}{rest}"))
         };
-        let lexeme = &self.raw[start..=end];
-        let literal = &self.raw[start + 1..end];
-        Ok(Token::new(Type::String, lexeme, Literal::String(literal)))
+        Error::lexical(self.span(start, end), kind)
     }
 
-    fn num(&mut self, start: usize) -> Token<'a> {
-        let mut seen_dot = false;
-        let mut end = start;
+    /// Scans the escape sequence whose backslash is at byte `start`, `None` at end of input.
+    fn escape(&mut self, start: usize) -> Result<Option<char>, Error> {
+        let (line, line_start) = (self.line, self.line_start);
+        let Some((i, c)) = self.chars.next() else {
+            return Ok(None);
+        };
+        let c = match c {
+            '\n' => {
+                self.newline(i);
+                None
+            }
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '"' => Some('"'),
+            '\\' => Some('\\'),
+            'u' => self.unicode_escape(),
+            _ => None,
+        };
+        let end = self.offset();
+        c.map(Some).ok_or_else(|| {
+            Error::lexical(
+                self.span_on(line, line_start, start, end),
+                LexicalKind::InvalidEscape(self.raw[start..end].to_owned()),
+            )
+        })
+    }
+
+    /// Scans the `{XXXX}` that follows `\u`, with between one and six hex digits.
+    fn unicode_escape(&mut self) -> Option<char> {
+        self.chars.peeking_next(|&(_, c)| c == '{')?;
+        let digits = self
+            .chars
+            .peeking_take_while(|(_, c)| c.is_ascii_hexdigit())
+            .map(|(_, c)| c)
+            .collect::<String>();
+        self.chars.peeking_next(|&(_, c)| c == '}')?;
+        if digits.is_empty() || digits.len() > 6 {
+            return None;
+        }
+        u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+    }
 
-        while let Some((_, c)) = self.chars.peek() {
+    /// Skips a block comment opened at byte `start`, along with any comments nested in it.
+    fn block_comment(&mut self, start: usize) -> Result<(), Error> {
+        let mut depth = 1;
+        while let Some((i, c)) = self.chars.next() {
             match c {
-                c if c.is_ascii_digit() => (),
-                '.' => {
-                    let is_next_digit = self
-                        .raw
-                        .as_bytes()
-                        .get(end + 2)
-                        .is_some_and(u8::is_ascii_digit);
-                    if seen_dot || !is_next_digit {
-                        break;
+                '/' if self.chars.peeking_next(|(_, c)| *c == '*').is_some() => depth += 1,
+                '*' if self.chars.peeking_next(|(_, c)| *c == '/').is_some() => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(());
                     }
-                    seen_dot = true;
                 }
-                _ => break,
+                '\n' => self.newline(i),
+                _ => (),
             }
+        }
+        Err(Error::lexical(
+            self.span(start, self.raw.len()),
+            LexicalKind::UnterminatedComment(self.token_line),
+        ))
+    }
+
+    /// Scans an identifier or keyword, normalizing it to NFC so that equivalent spellings of a
+    /// name compare equal.
+    fn identifier(&mut self, start: usize) -> Token<'a> {
+        self.chars
+            .peeking_take_while(|(_, c)| c.is_xid_continue())
+            .for_each(|_| ());
+        let identifier = &self.raw[start..self.offset()];
+        let identifier = if is_nfc(identifier) {
+            Cow::Borrowed(identifier)
+        } else {
+            Cow::Owned(identifier.nfc().collect())
+        };
+        Token::reserved()
+            .get(&identifier)
+            .cloned()
+            .unwrap_or_else(|| Token::identifier(identifier))
+    }
+
+    /// Byte offset of the next character.
+    fn offset(&mut self) -> usize {
+        self.chars.peek().map_or(self.raw.len(), |&(i, _)| i)
+    }
+
+    /// Byte offset at which scanning continues, and the line it is on.
+    pub(crate) fn position(&mut self) -> (usize, usize) {
+        (self.offset(), self.line)
+    }
+
+    /// Scans a number literal whose first digit is at byte `start`.
+    fn num(&mut self, start: usize) -> Result<Token<'a>, Error> {
+        let prefix = self
+            .chars
+            .peeking_next(|&(_, c)| self.raw[start..].starts_with('0') && "xbo".contains(c));
+        let value = match prefix {
+            Some((_, prefix)) => self.radix_num(start, prefix),
+            None => self.decimal_num(start),
+        };
+        let end = self.offset();
+        let num = &self.raw[start..end];
+        value
+            .map(|value| Token::new(Type::Number, num, Literal::Number(value)))
+            .map_err(|kind| {
+                Error::lexical(self.span(start, end), LexicalKind::MalformedNumber(kind))
+            })
+    }
+
+    /// Scans the digits of a `0x`, `0b` or `0o` literal, the prefix having been consumed.
+    fn radix_num(&mut self, start: usize, prefix: char) -> Result<f64, NumberKind> {
+        let radix = match prefix {
+            'x' => 16,
+            'b' => 2,
+            _ => 8,
+        };
+        self.chars
+            .peeking_take_while(|(_, c)| c.is_ascii_alphanumeric() || *c == '_')
+            .for_each(|_| ());
+        let digits = &self.raw[start + 2..self.offset()];
+
+        if !separates_digits(digits, u8::is_ascii_alphanumeric) {
+            return Err(NumberKind::TrailingSeparator);
+        }
+        let mut value: Option<f64> = None;
+        for digit in digits.chars().filter(|c| *c != '_') {
+            let d = digit
+                .to_digit(radix)
+                .ok_or(NumberKind::InvalidDigit { digit, radix })?;
+            value = Some(value.unwrap_or(0.0).mul_add(f64::from(radix), f64::from(d)));
+        }
+        value.ok_or(NumberKind::MissingDigits(prefix))
+    }
+
+    /// Scans the rest of a decimal literal, with optional fraction and exponent.
+    fn decimal_num(&mut self, start: usize) -> Result<f64, NumberKind> {
+        let is_digit = |&(_, c): &(usize, char)| c.is_ascii_digit() || c == '_';
+        self.chars.peeking_take_while(is_digit).for_each(|_| ());
+
+        let is_fraction = self.chars.peek().is_some_and(|&(i, c)| {
+            c == '.'
+                && self
+                    .raw
+                    .as_bytes()
+                    .get(i + 1)
+                    .is_some_and(u8::is_ascii_digit)
+        });
+        if is_fraction {
             self.chars.next();
-            end += 1;
+            self.chars.peeking_take_while(is_digit).for_each(|_| ());
+        }
+
+        if self
+            .chars
+            .peeking_next(|(_, c)| matches!(c, 'e' | 'E'))
+            .is_some()
+        {
+            self.chars.peeking_next(|(_, c)| matches!(c, '+' | '-'));
+            let digits = self
+                .chars
+                .peeking_take_while(is_digit)
+                .filter(|(_, c)| *c != '_')
+                .count();
+            if digits == 0 {
+                return Err(NumberKind::MissingExponent);
+            }
         }
 
-        let num = &self.raw[start..=end];
-        Token::new(
-            Type::Number,
-            num,
-            Literal::Number(num.parse::<f64>().unwrap()),
-        )
+        let num = &self.raw[start..self.offset()];
+        if !separates_digits(num, u8::is_ascii_digit) {
+            return Err(NumberKind::TrailingSeparator);
+        }
+        Ok(num
+            .replace('_', "")
+            .parse()
+            .expect("scanned literals are valid floats"))
     }
 }
 
+/// Whether every `_` in `num` comes right between two bytes that are `is_digit`.
+fn separates_digits(num: &str, is_digit: fn(&u8) -> bool) -> bool {
+    let bytes = num.as_bytes();
+    bytes
+        .iter()
+        .enumerate()
+        .filter(|&(_, &b)| b == b'_')
+        .all(|(i, _)| i > 0 && is_digit(&bytes[i - 1]) && bytes.get(i + 1).is_some_and(is_digit))
+}
+
 impl<'a> Iterator for Scanner<'a> {
     type Item = Result<Token<'a>, Error>;
 
@@ -107,6 +513,7 @@ impl<'a> Iterator for Scanner<'a> {
         }
 
         while let Some((i, c)) = self.chars.next() {
+            (self.token_line, self.token_line_start) = (self.line, self.line_start);
             let token = match c {
                 '(' => Token::LEFT_PAREN,
                 ')' => Token::RIGHT_PAREN,
@@ -121,12 +528,18 @@ impl<'a> Iterator for Scanner<'a> {
 
                 '\t' | '\x0C' | '\r' | ' ' => continue,
                 '\n' => {
-                    self.line += 1;
+                    self.newline(i);
                     continue;
                 }
 
-                c if c.is_ascii_digit() => return Some(Ok(self.num(i))),
-                '"' => return Some(self.string(i)),
+                c if c.is_ascii_digit() => match self.num(i) {
+                    Ok(token) => token,
+                    Err(e) => return Some(Err(e)),
+                },
+                '"' => match self.string(i) {
+                    Ok(token) => token,
+                    Err(e) => return Some(Err(e)),
+                },
 
                 '/' => {
                     if self.chars.peeking_next(|(_, c)| *c == '/').is_some() {
@@ -135,6 +548,12 @@ impl<'a> Iterator for Scanner<'a> {
                             .for_each(|_| ());
                         continue;
                     }
+                    if self.chars.peeking_next(|(_, c)| *c == '*').is_some() {
+                        match self.block_comment(i) {
+                            Ok(()) => continue,
+                            Err(e) => return Some(Err(e)),
+                        }
+                    }
                     Token::SLASH
                 }
                 '!' => self
@@ -153,36 +572,63 @@ impl<'a> Iterator for Scanner<'a> {
                     .chars
                     .peeking_next(|(_, c)| *c == '=')
                     .map_or(Token::GREATER, |_| Token::GREATER_EQUAL),
-                'a'..='z' | 'A'..='Z' | '_' => {
-                    let end = i + self
-                        .chars
-                        .peeking_take_while(|(_, c)| c.is_ascii_alphanumeric() || *c == '_')
-                        .count();
-                    let identifier = &self.raw[i..=end];
-                    Token::reserved()
-                        .get(identifier)
-                        .copied()
-                        .unwrap_or_else(|| Token::new_null(Type::Identifier, identifier))
-                }
-                c => {
-                    return Some(Err(Error::lexical(
-                        self.line,
-                        LexicalKind::UnexpectedCharacter(c),
-                    )))
-                }
+                c if c == '_' || c.is_xid_start() => self.identifier(i),
+                c => return Some(Err(self.unexpected(i, c))),
             };
-            return Some(Ok(token));
+            let end = self.offset();
+            let span = self.span(i, end);
+            return Some(Ok(token.with_span(span)));
         }
         self.eof = true;
-        Some(Ok(Token::EOF))
+        (self.token_line, self.token_line_start) = (self.line, self.line_start);
+        let span = self.span(self.raw.len(), self.raw.len());
+        Some(Ok(Token::EOF.with_span(span)))
     }
 }
 
+/// Whether `c` is whitespace or can start a token.
+fn starts_token(c: char) -> bool {
+    matches!(
+        c,
+        '(' | ')'
+            | '{'
+            | '}'
+            | ';'
+            | ','
+            | '+'
+            | '-'
+            | '*'
+            | '.'
+            | '/'
+            | '!'
+            | '='
+            | '<'
+            | '>'
+            | '"'
+            | '_'
+            | '\t'
+            | '\x0C'
+            | '\r'
+            | ' '
+            | '\n'
+    ) || c.is_ascii_digit()
+        || c.is_xid_start()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use pretty_assertions::assert_eq;
 
+    const fn span(start: usize, end: usize, line: usize, column: usize) -> Span {
+        Span {
+            start,
+            end,
+            line,
+            column,
+        }
+    }
+
     #[test]
     fn punctuators() {
         let input = "(){};,+-*!!====<=>=!=<>/.";
@@ -243,16 +689,80 @@ mod tests {
         assert_eq!(next_token().unwrap(), Token::STAR);
         assert_eq!(
             next_token().unwrap(),
-            Token::new(Type::String, "\"*string*\"", Literal::String("*string*"))
+            Token::new(
+                Type::String,
+                "\"*string*\"",
+                Literal::String("*string*".into())
+            )
         );
         assert_eq!(
             next_token(),
-            Err(Error::lexical(4, LexicalKind::UnterminatedString))
+            Err(Error::lexical(
+                span(24, 42, 4, 1),
+                LexicalKind::UnterminatedString
+            ))
         );
         assert_eq!(next_token().unwrap(), Token::EOF);
         assert!(scanner.next().is_none());
     }
 
+    #[test]
+    fn recovery() {
+        let input = "print @@ $;\nvar s = \"oops;\nprint \u{a7}\u{b6}# 1;\n";
+        let errors = Scanner::new(input)
+            .with_recovery()
+            .filter_map(Result::err)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [
+                Error::lexical(
+                    span(6, 8, 1, 7),
+                    LexicalKind::UnexpectedSequence("@@".into())
+                ),
+                Error::lexical(span(9, 10, 1, 10), LexicalKind::UnexpectedCharacter('$')),
+                Error::lexical(span(20, 26, 2, 9), LexicalKind::UnterminatedString),
+                Error::lexical(
+                    span(33, 38, 3, 7),
+                    LexicalKind::UnexpectedSequence("\u{a7}\u{b6}#".into())
+                ),
+            ]
+        );
+        let tokens = Scanner::new(input)
+            .with_recovery()
+            .filter_map(Result::ok)
+            .map(|x| x.ty)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            [
+                Type::Print,
+                Type::Semicolon,
+                Type::Var,
+                Type::Identifier,
+                Type::Equal,
+                Type::Print,
+                Type::Number,
+                Type::Semicolon,
+                Type::Eof,
+            ]
+        );
+
+        // Without recovery, every character is an error and the string runs to the end.
+        let errors = Scanner::new(input)
+            .filter_map(Result::err)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [
+                Error::lexical(span(6, 7, 1, 7), LexicalKind::UnexpectedCharacter('@')),
+                Error::lexical(span(7, 8, 1, 8), LexicalKind::UnexpectedCharacter('@')),
+                Error::lexical(span(9, 10, 1, 10), LexicalKind::UnexpectedCharacter('$')),
+                Error::lexical(span(20, 42, 2, 9), LexicalKind::UnterminatedString),
+            ]
+        );
+    }
+
     #[test]
     fn multi_line() {
         let multi_line = "\
@@ -275,13 +785,16 @@ mod tests {
             Token::new(
                 Type::String,
                 multi_line,
-                Literal::String(&multi_line[1..multi_line.len() - 1])
+                Literal::String(multi_line[1..multi_line.len() - 1].into())
             )
         );
 
         assert_eq!(
             next_token(),
-            Err(Error::lexical(6, LexicalKind::UnexpectedCharacter('$')))
+            Err(Error::lexical(
+                span(30, 31, 6, 9),
+                LexicalKind::UnexpectedCharacter('$')
+            ))
         );
         assert_eq!(next_token().unwrap(), Token::STAR);
         assert_eq!(next_token().unwrap(), Token::EOF);
@@ -347,10 +860,10 @@ mod tests {
 
         let mut scanner = Scanner::new(input);
         let mut next_token = || scanner.next().unwrap().unwrap();
-        let reserved = |token: &str| {
+        let reserved = |token: Cow<str>| {
             Token::reserved()
-                .get(token)
-                .copied()
+                .get(&*token)
+                .cloned()
                 .unwrap_or_else(|| panic!("not reserved: \"{token}\""))
         };
         assert_eq!(reserved(next_token().lexeme), Token::AND);
@@ -371,4 +884,199 @@ mod tests {
         assert_eq!(reserved(next_token().lexeme), Token::WHILE);
         assert_eq!(next_token(), Token::EOF);
     }
+
+    #[test]
+    fn spans() {
+        let input = "\
+        var ab = \"x\ny\";\n\
+        \t!= 1.5\n\
+        ";
+        let spans = Scanner::new(input)
+            .map(|token| {
+                let token = token.unwrap();
+                let span = token.span();
+                (&input[span.start..span.end], span)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            spans,
+            [
+                ("var", span(0, 3, 1, 1)),
+                ("ab", span(4, 6, 1, 5)),
+                ("=", span(7, 8, 1, 8)),
+                ("\"x\ny\"", span(9, 14, 1, 10)),
+                (";", span(14, 15, 2, 3)),
+                ("!=", span(17, 19, 3, 2)),
+                ("1.5", span(20, 23, 3, 5)),
+                ("", span(24, 24, 4, 1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn unicode_columns() {
+        let input = "\"héllo\" +";
+        let mut scanner = Scanner::new(input);
+        let string = scanner.next().unwrap().unwrap();
+        assert_eq!(string.literal, Literal::String("héllo".into()));
+        let plus = scanner.next().unwrap().unwrap();
+        assert_eq!(plus.span(), span(9, 10, 1, 9));
+    }
+
+    #[test]
+    fn long_line_columns() {
+        let input = "\"\u{e9}\" ".repeat(10_000) + "\"\\q\" +";
+        let spans = Scanner::new(&input)
+            .map(|token| token.map_or_else(|e| e.span().unwrap(), |token| token.span()))
+            .skip(10_000)
+            .collect::<Vec<_>>();
+        let start = 50_000;
+        assert_eq!(
+            spans,
+            [
+                span(start + 1, start + 3, 1, 40_002),
+                span(start + 5, start + 6, 1, 40_006),
+                span(start + 6, start + 6, 1, 40_007),
+            ]
+        );
+    }
+
+    #[test]
+    fn escapes() {
+        let mut scanner = Scanner::new(r#""plain" "a\tb\n\"c\\\u{1F600}""#);
+        let plain = scanner.next().unwrap().unwrap();
+        assert!(matches!(
+            plain.literal,
+            Literal::String(Cow::Borrowed("plain"))
+        ));
+        let escaped = scanner.next().unwrap().unwrap();
+        assert_eq!(escaped.lexeme, r#""a\tb\n\"c\\\u{1F600}""#);
+        assert_eq!(
+            escaped.literal,
+            Literal::String("a\tb\n\"c\\\u{1F600}".into())
+        );
+    }
+
+    #[test]
+    fn invalid_escapes() {
+        let mut scanner = Scanner::new("\"a\\qb\" 1\n\"\\u{110000}\\u{}\"");
+        assert_eq!(
+            scanner.next().unwrap(),
+            Err(Error::lexical(
+                span(2, 4, 1, 3),
+                LexicalKind::InvalidEscape("\\q".into())
+            ))
+        );
+        assert_eq!(
+            scanner.next().unwrap().unwrap(),
+            Token::new(Type::Number, "1", Literal::Number(1.0))
+        );
+        assert_eq!(
+            scanner.next().unwrap(),
+            Err(Error::lexical(
+                span(10, 20, 2, 2),
+                LexicalKind::InvalidEscape("\\u{110000}".into())
+            ))
+        );
+        assert_eq!(scanner.next().unwrap().unwrap(), Token::EOF);
+    }
+
+    #[test]
+    fn escaped_newline() {
+        let input = "\"a\\\nb\"\n$";
+        let errors = Scanner::new(input)
+            .filter_map(Result::err)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [
+                Error::lexical(span(2, 4, 1, 3), LexicalKind::InvalidEscape("\\\n".into())),
+                Error::lexical(span(7, 8, 3, 1), LexicalKind::UnexpectedCharacter('$')),
+            ]
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "[line 1] Error: Invalid escape sequence: \\\\n"
+        );
+    }
+
+    #[test]
+    fn block_comments() {
+        let input = "/* a /* nested\n */ still\n comment */ + /**/ -\n/* open /* */";
+        let mut scanner = Scanner::new(input);
+        let plus = scanner.next().unwrap().unwrap();
+        assert_eq!((plus.ty, plus.span()), (Type::Plus, span(37, 38, 3, 13)));
+        let minus = scanner.next().unwrap().unwrap();
+        assert_eq!((minus.ty, minus.span()), (Type::Minus, span(44, 45, 3, 20)));
+        assert_eq!(
+            scanner.next().unwrap(),
+            Err(Error::lexical(
+                span(46, 59, 4, 1),
+                LexicalKind::UnterminatedComment(4)
+            ))
+        );
+        assert_eq!(scanner.next().unwrap().unwrap(), Token::EOF);
+    }
+
+    #[test]
+    fn number_syntax() {
+        let input = "0xFF 0b1010 0o17 1_000.5 1.5e-3 2E10 0x 1_ 1__0 0x_FF 1e_3 1e+ 0b102 0";
+        let numbers = Scanner::new(input)
+            .map(|token| match token {
+                Ok(token) => Ok((&input[token.span.start..token.span.end], token.literal)),
+                Err(Error::Lexical {
+                    kind: LexicalKind::MalformedNumber(kind),
+                    span,
+                }) => Err((&input[span.start..span.end], kind)),
+                Err(e) => panic!("unexpected error: {e}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            numbers,
+            [
+                Ok(("0xFF", Literal::Number(255.0))),
+                Ok(("0b1010", Literal::Number(10.0))),
+                Ok(("0o17", Literal::Number(15.0))),
+                Ok(("1_000.5", Literal::Number(1000.5))),
+                Ok(("1.5e-3", Literal::Number(0.0015))),
+                Ok(("2E10", Literal::Number(2e10))),
+                Err(("0x", NumberKind::MissingDigits('x'))),
+                Err(("1_", NumberKind::TrailingSeparator)),
+                Err(("1__0", NumberKind::TrailingSeparator)),
+                Err(("0x_FF", NumberKind::TrailingSeparator)),
+                Err(("1e_3", NumberKind::TrailingSeparator)),
+                Err(("1e+", NumberKind::MissingExponent)),
+                Err((
+                    "0b102",
+                    NumberKind::InvalidDigit {
+                        digit: '2',
+                        radix: 2
+                    }
+                )),
+                Ok(("0", Literal::Number(0.0))),
+                Ok(("", Literal::Null)),
+            ]
+        );
+    }
+
+    #[test]
+    fn unicode_identifiers() {
+        let input = "caf\u{e9} cafe\u{301} \u{3c0}_2 _x \u{2603}";
+        let mut scanner = Scanner::new(input);
+        let mut next_token = || scanner.next().unwrap();
+        let composed = next_token().unwrap();
+        assert_eq!(composed, Token::identifier("caf\u{e9}".into()));
+        let decomposed = next_token().unwrap();
+        assert_eq!(decomposed, composed);
+        assert_eq!(decomposed.span(), span(6, 12, 1, 6));
+        assert_eq!(next_token().unwrap(), Token::identifier("\u{3c0}_2".into()));
+        assert_eq!(next_token().unwrap(), Token::identifier("_x".into()));
+        assert_eq!(
+            next_token(),
+            Err(Error::lexical(
+                span(21, 24, 1, 19),
+                LexicalKind::UnexpectedCharacter('\u{2603}')
+            ))
+        );
+    }
 }
@@ -0,0 +1,272 @@
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead},
+    str,
+};
+
+use crate::{
+    scanner::{Error, LexicalKind},
+    Scanned, Scanner, Span, Type,
+};
+
+/// Bytes read before scanning them, unless a token needs more.
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Scans a program while reading it, for inputs too large to hold in memory at once.
+///
+/// Tokens own their text, so only the token currently being scanned is kept around, even when
+/// the whole input is on a single line. Spans are relative to the whole input, as if it had been
+/// given to [`Scanner::new`].
+pub struct Stream<R> {
+    reader: R,
+    /// Input read but not scanned yet, starting between two tokens.
+    buf: String,
+    /// Bytes read after `buf` that don't form a whole character yet.
+    partial: Vec<u8>,
+    /// Byte offset of `buf` in the whole input, and the line and column it starts at.
+    base: usize,
+    line: usize,
+    column: usize,
+    /// Bytes to read before scanning again.
+    chunk: usize,
+    chunk_size: usize,
+    scanned: VecDeque<Scanned<'static>>,
+    done: bool,
+}
+
+impl<R: BufRead> Stream<R> {
+    pub const fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buf: String::new(),
+            partial: Vec::new(),
+            base: 0,
+            line: 1,
+            column: 1,
+            chunk: CHUNK_SIZE,
+            chunk_size: CHUNK_SIZE,
+            scanned: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    /// Scans whenever `bytes` more have been read, rather than every 8 KiB.
+    #[must_use]
+    pub const fn with_chunk_size(mut self, bytes: usize) -> Self {
+        let bytes = if bytes == 0 { 1 } else { bytes };
+        self.chunk = bytes;
+        self.chunk_size = bytes;
+        self
+    }
+
+    /// Reads until at least `chunk` more bytes are buffered, returning whether the end of the
+    /// input was reached.
+    fn fill(&mut self) -> io::Result<bool> {
+        let len = self.buf.len() + self.partial.len();
+        while self.buf.len() + self.partial.len() - len < self.chunk {
+            let bytes = self.reader.fill_buf()?;
+            if bytes.is_empty() {
+                if !self.partial.is_empty() {
+                    let e = str::from_utf8(&self.partial).expect_err("a partial character");
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, e));
+                }
+                return Ok(true);
+            }
+            let wanted = self.chunk - (self.buf.len() + self.partial.len() - len);
+            let read = bytes.len().min(wanted);
+            self.partial.extend_from_slice(&bytes[..read]);
+            self.reader.consume(read);
+
+            let valid = match str::from_utf8(&self.partial) {
+                Ok(_) => self.partial.len(),
+                Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+            };
+            let text = str::from_utf8(&self.partial[..valid]).expect("validated above");
+            self.buf.push_str(text);
+            self.partial.drain(..valid);
+        }
+        Ok(false)
+    }
+
+    /// Scans what has been read. Unless that is all of the input, the last tokens might continue
+    /// in what hasn't, so scanning stops before them to try again once more has been read.
+    fn scan(&mut self, eof: bool) {
+        let before = self.scanned.len();
+        let mut scanner = Scanner::resume(&self.buf, 0, self.line);
+        let mut resume = (0, self.line);
+        while let Some(item) = scanner.next() {
+            let (offset, line) = scanner.position();
+            if !eof && !complete(&item, &self.buf[offset..]) {
+                break;
+            }
+            let item = rebased(item, self.base, self.line, self.column);
+            self.scanned.push_back(item);
+            resume = (offset, line);
+        }
+        if eof {
+            self.done = true;
+            return;
+        }
+
+        // A token that doesn't fit gets twice as much input the next time around.
+        self.chunk = if self.scanned.len() > before {
+            self.chunk_size
+        } else {
+            self.chunk.saturating_mul(2)
+        };
+        let (end, line) = resume;
+        self.column = match self.buf[..end].rfind('\n') {
+            Some(i) => self.buf[i + 1..end].chars().count() + 1,
+            None => self.column + self.buf[..end].chars().count(),
+        };
+        self.buf.drain(..end);
+        self.base += end;
+        self.line = line;
+    }
+}
+
+impl<R: BufRead> Iterator for Stream<R> {
+    type Item = io::Result<Scanned<'static>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.scanned.pop_front() {
+                return Some(Ok(item));
+            }
+            if self.done {
+                return None;
+            }
+            match self.fill() {
+                Ok(eof) => self.scan(eof),
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+/// Whether `item` stays the same however the input goes on after `rest`, what follows it in
+/// what has been read. Strings and comments run until they are closed, and other tokens end
+/// depending on at most the next two characters, as `1` does before `.5`.
+fn complete(item: &Scanned, rest: &str) -> bool {
+    let unterminated = match item {
+        Ok(token) => matches!(token.ty, Type::Eof),
+        Err(Error::Lexical { kind, .. }) => matches!(
+            kind,
+            LexicalKind::UnterminatedString | LexicalKind::UnterminatedComment(_)
+        ),
+    };
+    !unterminated && rest.chars().nth(1).is_some()
+}
+
+/// `item`, scanned from a part of the input starting at byte `base` and at `column` of `line`,
+/// owning its text.
+fn rebased(item: Scanned, base: usize, line: usize, column: usize) -> Scanned<'static> {
+    let shift = |span: Span| Span {
+        start: span.start + base,
+        end: span.end + base,
+        column: if span.line == line {
+            span.column + column - 1
+        } else {
+            span.column
+        },
+        ..span
+    };
+    match item {
+        Ok(token) => {
+            let span = shift(token.span);
+            Ok(token.with_span(span).into_owned())
+        }
+        Err(Error::Lexical { span, kind }) => Err(Error::lexical(shift(span), kind)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    use crate::relex::span_of;
+
+    fn spanned<'a>(items: impl IntoIterator<Item = Scanned<'a>>) -> Vec<(Scanned<'a>, Span)> {
+        items
+            .into_iter()
+            .map(|x| {
+                let span = span_of(&x);
+                (x, span)
+            })
+            .collect()
+    }
+
+    fn check(input: &str, chunk_size: usize) {
+        let streamed = Stream::new(input.as_bytes())
+            .with_chunk_size(chunk_size)
+            .map(Result::unwrap);
+        assert_eq!(
+            spanned(streamed),
+            spanned(Scanner::new(input)),
+            "{input:?} in chunks of {chunk_size}"
+        );
+    }
+
+    #[test]
+    fn matches_full_scan() {
+        let inputs = [
+            "",
+            "\n\n",
+            "print 1;",
+            "var a = 1; // one\r\nvar b = 2;\n\nprint a + b;\n",
+            "var s = \"multi\nline\n\nstring\"; print s;\n",
+            "/* block\n /* nested */\n comment */ print caf\u{e9};\n",
+            "print \"\\u{e9}\\q\nescapes\";\nprint @;\n",
+            "print 1;\n\"unterminated\nstring",
+            "print 1;\n/* unterminated\ncomment",
+            "var x = 0x1F + 1_000.5e-3;\nprint x >= 1 and x != 2;",
+        ];
+        for input in inputs {
+            for chunk_size in [0, 1, 5, 16, CHUNK_SIZE] {
+                check(input, chunk_size);
+            }
+        }
+    }
+
+    #[test]
+    fn long_tokens() {
+        let lines = (0..1000)
+            .map(|i| i.to_string() + " line\n")
+            .collect::<String>();
+        let input = format!("var s = \"{lines}\";\n/*{lines}*/ print s;\n");
+        check(&input, 64);
+    }
+
+    #[test]
+    fn long_line() {
+        let input = "print \"\u{e9}\" + a.b(1.5, 2) /* \u{e9} */ >= !c; ".repeat(2000);
+        check(&input, 16);
+
+        let mut stream = Stream::new(input.as_bytes()).with_chunk_size(16);
+        let mut longest = 0;
+        while let Some(item) = stream.next() {
+            assert!(item.is_ok());
+            longest = longest.max(stream.buf.len());
+        }
+        assert!(longest < 64, "{longest} bytes buffered");
+    }
+
+    #[test]
+    fn split_characters() {
+        check("print \"\u{1F600}\u{e9}\"; var caf\u{e9} = \u{2603};", 1);
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let mut stream = Stream::new(&b"print 1;\nprint \xff;\n"[..]).with_chunk_size(1);
+        assert!(stream.next().unwrap().is_ok());
+        assert!(stream.by_ref().find_map(Result::err).is_some());
+        assert!(stream.next().is_none());
+    }
+}
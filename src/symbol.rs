@@ -0,0 +1,216 @@
+use std::{borrow::Cow, collections::HashMap, rc::Rc};
+
+use crate::{Literal, Span, Token, Type};
+
+/// An interned string, equal to another symbol from the same [`Interner`] exactly when their
+/// strings are equal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    #[must_use]
+    pub const fn id(self) -> u32 {
+        self.0
+    }
+}
+
+/// Hands out a [`Symbol`] for every distinct string it is given.
+#[derive(Debug, Default)]
+pub struct Interner {
+    symbols: HashMap<Rc<str>, Symbol>,
+    strings: Vec<Rc<str>>,
+}
+
+impl Interner {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// # Panics
+    ///
+    /// If there are already 2^32 distinct strings.
+    pub fn intern(&mut self, string: &str) -> Symbol {
+        if let Some(&symbol) = self.symbols.get(string) {
+            return symbol;
+        }
+        let symbol =
+            Symbol(u32::try_from(self.strings.len()).expect("fewer than 2^32 distinct strings"));
+        let string = Rc::<str>::from(string);
+        self.strings.push(string.clone());
+        self.symbols.insert(string, symbol);
+        symbol
+    }
+
+    /// The symbol of `string`, if it was interned.
+    #[must_use]
+    pub fn get(&self, string: &str) -> Option<Symbol> {
+        self.symbols.get(string).copied()
+    }
+
+    /// The string `symbol` was interned from.
+    ///
+    /// # Panics
+    ///
+    /// If `symbol` comes from another interner.
+    #[must_use]
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        &self.strings[symbol.0 as usize]
+    }
+
+    /// Like [`resolve`](Self::resolve), but sharing the string so it can outlive the interner.
+    #[must_use]
+    pub fn resolve_shared(&self, symbol: Symbol) -> Rc<str> {
+        Rc::clone(&self.strings[symbol.0 as usize])
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// `token` with its lexeme and string literal interned.
+    pub fn token(&mut self, token: &Token) -> OwnedToken {
+        let literal = match &token.literal {
+            Literal::Null => OwnedLiteral::Null,
+            Literal::String(x) => OwnedLiteral::String(self.intern(x)),
+            Literal::Number(x) => OwnedLiteral::Number(*x),
+        };
+        OwnedToken {
+            ty: token.ty,
+            lexeme: self.intern(&token.lexeme),
+            literal,
+            span: token.span,
+        }
+    }
+}
+
+/// A [`Token`] that doesn't borrow from the source, its text lives in an [`Interner`] instead.
+#[derive(Debug, Clone, Copy)]
+pub struct OwnedToken {
+    pub(crate) ty: Type,
+    pub(crate) lexeme: Symbol,
+    pub(crate) literal: OwnedLiteral,
+    pub(crate) span: Span,
+}
+
+/// The value of an [`OwnedToken`] that is a literal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OwnedLiteral {
+    Null,
+    String(Symbol),
+    Number(f64),
+}
+
+impl OwnedToken {
+    #[must_use]
+    pub const fn ty(&self) -> Type {
+        self.ty
+    }
+
+    /// The lexeme, which for identifiers is the name.
+    #[must_use]
+    pub const fn lexeme(&self) -> Symbol {
+        self.lexeme
+    }
+
+    /// The value of a string literal, `None` for any other token.
+    #[must_use]
+    pub const fn string(&self) -> Option<Symbol> {
+        match self.literal {
+            OwnedLiteral::String(x) => Some(x),
+            OwnedLiteral::Null | OwnedLiteral::Number(_) => None,
+        }
+    }
+
+    #[must_use]
+    pub const fn literal(&self) -> OwnedLiteral {
+        self.literal
+    }
+
+    #[must_use]
+    pub const fn span(&self) -> Span {
+        self.span
+    }
+
+    /// This token borrowing its text from `interner`, which must be the one it was interned
+    /// into.
+    #[must_use]
+    pub fn resolve<'i>(&self, interner: &'i Interner) -> Token<'i> {
+        let literal = match self.literal {
+            OwnedLiteral::Null => Literal::Null,
+            OwnedLiteral::String(x) => Literal::String(Cow::Borrowed(interner.resolve(x))),
+            OwnedLiteral::Number(x) => Literal::Number(x),
+        };
+        Token {
+            ty: self.ty,
+            lexeme: Cow::Borrowed(interner.resolve(self.lexeme)),
+            literal,
+            span: self.span,
+        }
+    }
+}
+
+/// Spans are ignored, like for [`Token`].
+impl PartialEq for OwnedToken {
+    fn eq(&self, other: &Self) -> bool {
+        self.ty == other.ty && self.lexeme == other.lexeme && self.literal == other.literal
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Scanner;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn interns() {
+        let mut interner = Interner::new();
+        let a = interner.intern("a");
+        let b = interner.intern("b");
+        assert_ne!(a, b);
+        assert_eq!(interner.intern("a"), a);
+        assert_eq!(interner.get("b"), Some(b));
+        assert_eq!(interner.get("c"), None);
+        assert_eq!(interner.resolve(b), "b");
+        assert_eq!(interner.len(), 2);
+    }
+
+    #[test]
+    fn outlive_source() {
+        let mut interner = Interner::new();
+        let source = String::from("var caf\u{e9} = \"x\\ty\"; print cafe\u{301} + 1;");
+        let tokens = Scanner::new(&source)
+            .map(|x| interner.token(&x.unwrap()))
+            .collect::<Vec<_>>();
+        let expected = Scanner::new(&source)
+            .map(|x| {
+                let token = x.unwrap();
+                (token.to_string(), token.span())
+            })
+            .collect::<Vec<_>>();
+        drop(source);
+
+        assert_eq!(tokens[1].lexeme(), tokens[6].lexeme());
+        assert_eq!(tokens[1].ty(), Type::Identifier);
+        assert_eq!(tokens[5].literal(), OwnedLiteral::Null);
+        assert_eq!(tokens[8].literal(), OwnedLiteral::Number(1.0));
+        assert_eq!(tokens[3].string(), Some(interner.intern("x\ty")));
+        assert_eq!(tokens[1].string(), None);
+        let resolved = tokens
+            .iter()
+            .map(|x| {
+                let token = x.resolve(&interner);
+                (token.to_string(), token.span())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(resolved, expected);
+    }
+}
@@ -1,10 +1,28 @@
-use std::fmt::Display;
+use std::{borrow::Cow, fmt::Display};
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
 pub struct Token<'a> {
+    #[serde(rename = "type")]
     pub(crate) ty: Type,
-    pub(crate) lexeme: &'a str,
+    /// NFC-normalized for identifiers, verbatim from the source otherwise.
+    pub(crate) lexeme: Cow<'a, str>,
     pub(crate) literal: Literal<'a>,
+    pub(crate) span: Span,
+}
+
+/// Location of a token in the source.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize)]
+pub struct Span {
+    /// Byte offset of the first character.
+    pub start: usize,
+    /// Byte offset one past the last character.
+    pub end: usize,
+    /// 1-based line of the first character.
+    pub line: usize,
+    /// 1-based column, in characters, of the first character.
+    pub column: usize,
 }
 
 impl<'a> Token<'a> {
@@ -52,8 +70,42 @@ impl<'a> Token<'a> {
     pub(crate) const fn new(ty: Type, lexeme: &'a str, literal: Literal<'a>) -> Self {
         Self {
             ty,
-            lexeme,
+            lexeme: Cow::Borrowed(lexeme),
             literal,
+            span: Span {
+                start: 0,
+                end: 0,
+                line: 0,
+                column: 0,
+            },
+        }
+    }
+
+    #[inline]
+    pub(crate) fn with_span(self, span: Span) -> Self {
+        Self { span, ..self }
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn ty(&self) -> Type {
+        self.ty
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Copies out whatever this token borrows from the source.
+    #[must_use]
+    pub fn into_owned(self) -> Token<'static> {
+        Token {
+            ty: self.ty,
+            lexeme: Cow::Owned(self.lexeme.into_owned()),
+            literal: self.literal.into_owned(),
+            span: self.span,
         }
     }
 
@@ -62,6 +114,14 @@ impl<'a> Token<'a> {
         Self::new(ty, lexeme, Literal::Null)
     }
 
+    #[inline]
+    pub(crate) fn identifier(name: Cow<'a, str>) -> Self {
+        Self {
+            lexeme: name,
+            ..Self::new_null(Type::Identifier, "")
+        }
+    }
+
     #[inline]
     pub(crate) fn reserved() -> &'static phf::Map<&'static str, Self> {
         static RESERVED: phf::Map<&'static str, Token> = phf::phf_map! {
@@ -86,8 +146,9 @@ impl<'a> Token<'a> {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, strum_macros::Display)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, strum_macros::Display, Serialize)]
 #[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum Type {
     LeftParen,
     RightParen,
@@ -134,20 +195,39 @@ pub enum Type {
     Eof,
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Serialize)]
+#[serde(untagged)]
 pub enum Literal<'a> {
     Null,
-    String(&'a str),
+    /// Borrowed from the source unless the string contains escapes.
+    String(Cow<'a, str>),
     Number(f64),
 }
 
-impl<'a> Display for Token<'a> {
+impl Literal<'_> {
+    pub(crate) fn into_owned(self) -> Literal<'static> {
+        match self {
+            Self::Null => Literal::Null,
+            Self::String(x) => Literal::String(Cow::Owned(x.into_owned())),
+            Self::Number(x) => Literal::Number(x),
+        }
+    }
+}
+
+/// Spans are ignored, tokens compare by what they are rather than where they are.
+impl PartialEq for Token<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ty == other.ty && self.lexeme == other.lexeme && self.literal == other.literal
+    }
+}
+
+impl Display for Token<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{} {} {}", self.ty, self.lexeme, self.literal)
     }
 }
 
-impl<'a> Display for Literal<'a> {
+impl Display for Literal<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Null => write!(f, "null"),
@@ -1,12 +1,92 @@
 use std::io::{self, Write};
 
-use crate::Scanner;
+use serde::Serialize;
+
+use crate::{
+    scanner::{self, LexicalKind},
+    Renderer, Scanner, Span, Style, Token,
+};
+
+/// How tokens are written out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    /// Tokens on `out` and errors on `err`, one per line.
+    #[default]
+    Text,
+    /// A single JSON array of tokens and errors on `out`.
+    Json,
+    /// One JSON object per token or error on `out`.
+    Jsonl,
+}
+
+/// How tokenizing went.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Outcome {
+    #[default]
+    Success,
+    /// The input had lexical errors, all tokens were still written unless told to stop early.
+    LexicalErrors,
+    /// Writing tokens or errors out failed.
+    IoFailure,
+}
+
+impl Outcome {
+    /// The matching exit code from `sysexits.h`.
+    #[must_use]
+    pub const fn code(self) -> i32 {
+        match self {
+            Self::Success => 0,
+            // EX_DATAERR
+            Self::LexicalErrors => 65,
+            // EX_IOERR
+            Self::IoFailure => 74,
+        }
+    }
+}
+
+/// A token or error as it appears in the JSON formats.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Entry<'a> {
+    Token(Token<'a>),
+    Error {
+        code: &'static str,
+        kind: LexicalKind,
+        message: String,
+        span: Span,
+    },
+}
+
+impl<'a> From<Result<Token<'a>, scanner::Error>> for Entry<'a> {
+    fn from(token: Result<Token<'a>, scanner::Error>) -> Self {
+        match token {
+            Ok(token) => Self::Token(token),
+            Err(e) => {
+                let message = e.to_string();
+                let scanner::Error::Lexical { span, kind } = e;
+                Self::Error {
+                    code: kind.code(),
+                    kind,
+                    message,
+                    span,
+                }
+            }
+        }
+    }
+}
 
 pub struct Tokenizer<'a, O, E> {
     scanner: Scanner<'a>,
     out: &'a mut O,
     err: &'a mut E,
-    pub code: i32,
+    diagnostics: Renderer<'a>,
+    format: Format,
+    outcome: Outcome,
+    errors: usize,
+    /// Errors past this many are counted but not reported.
+    max_errors: Option<usize>,
+    /// Whether to carry on after the first error.
+    keep_going: bool,
 }
 
 impl<'a, O, E> Tokenizer<'a, O, E>
@@ -20,23 +100,128 @@ where
             scanner,
             out,
             err,
-            code: 0,
+            diagnostics: Renderer::new(input),
+            format: Format::Text,
+            outcome: Outcome::Success,
+            errors: 0,
+            max_errors: None,
+            keep_going: true,
+        }
+    }
+
+    /// Reports errors in `style`, naming `file` as the origin of the input. Rich reports
+    /// [recover](Scanner::with_recovery) from errors.
+    #[must_use]
+    pub const fn with_diagnostics(mut self, style: Style, file: &'a str) -> Self {
+        self.diagnostics = self.diagnostics.with_style(style, file);
+        if matches!(style, Style::Rich) {
+            self.scanner = self.scanner.with_recovery();
+        }
+        self
+    }
+
+    /// The JSON formats [recover](Scanner::with_recovery) from errors.
+    #[must_use]
+    pub const fn with_format(mut self, format: Format) -> Self {
+        self.format = format;
+        if !matches!(format, Format::Text) {
+            self.scanner = self.scanner.with_recovery();
         }
+        self
+    }
+
+    /// Reports at most `max` errors, any more are only counted.
+    #[must_use]
+    pub const fn with_max_errors(mut self, max: usize) -> Self {
+        self.max_errors = Some(max);
+        self
+    }
+
+    /// Stops at the first error instead of tokenizing all of the input.
+    #[must_use]
+    pub const fn with_fail_fast(mut self) -> Self {
+        self.keep_going = false;
+        self
+    }
+
+    #[must_use]
+    pub const fn outcome(&self) -> Outcome {
+        self.outcome
+    }
+
+    /// Number of lexical errors found, reported or not.
+    #[must_use]
+    pub const fn errors(&self) -> usize {
+        self.errors
     }
 
     pub fn tokenize(&mut self) -> io::Result<()> {
-        for token in self.scanner.by_ref() {
+        let result = self.write();
+        if result.is_err() {
+            self.outcome = Outcome::IoFailure;
+        }
+        result
+    }
+
+    fn write(&mut self) -> io::Result<()> {
+        match self.format {
+            Format::Text => self.text()?,
+            Format::Json | Format::Jsonl => self.json()?,
+        }
+
+        self.out.flush()?;
+        self.err.flush()?;
+        Ok(())
+    }
+
+    /// Counts an error, returning whether to report it.
+    fn error(&mut self) -> bool {
+        self.errors += 1;
+        self.outcome = Outcome::LexicalErrors;
+        self.max_errors.map_or(true, |max| self.errors <= max)
+    }
+
+    const fn stopped(&self) -> bool {
+        !self.keep_going && self.errors > 0
+    }
+
+    fn text(&mut self) -> io::Result<()> {
+        while let Some(token) = self.scanner.next() {
             match token {
                 Ok(token) => writeln!(self.out, "{token}")?,
                 Err(e) => {
-                    self.code = 65; // TODO
-                    writeln!(self.err, "{e}")?;
+                    if self.error() {
+                        self.diagnostics.render(self.err, &e)?;
+                    }
                 }
-            };
+            }
+            if self.stopped() {
+                break;
+            }
         }
+        Ok(())
+    }
 
-        self.out.flush()?;
-        self.err.flush()?;
+    fn json(&mut self) -> io::Result<()> {
+        let mut entries = Vec::new();
+        while let Some(token) = self.scanner.next() {
+            if token.is_ok() || self.error() {
+                let entry = Entry::from(token);
+                if self.format == Format::Jsonl {
+                    serde_json::to_writer(&mut *self.out, &entry)?;
+                    writeln!(self.out)?;
+                } else {
+                    entries.push(entry);
+                }
+            }
+            if self.stopped() {
+                break;
+            }
+        }
+        if self.format == Format::Json {
+            serde_json::to_writer(&mut *self.out, &entries)?;
+            writeln!(self.out)?;
+        }
         Ok(())
     }
 }
@@ -46,7 +231,7 @@ mod tests {
     use super::*;
     use crate::{
         scanner::{Error, LexicalKind},
-        Token,
+        Span, Token,
     };
 
     use pretty_assertions::assert_eq;
@@ -88,8 +273,24 @@ mod tests {
         let expected = {
             let mut buf = Vec::new();
             let errors = [
-                Error::lexical(1, LexicalKind::UnexpectedCharacter('$')),
-                Error::lexical(2, LexicalKind::UnexpectedCharacter('#')),
+                Error::lexical(
+                    Span {
+                        start: 0,
+                        end: 1,
+                        line: 1,
+                        column: 1,
+                    },
+                    LexicalKind::UnexpectedCharacter('$'),
+                ),
+                Error::lexical(
+                    Span {
+                        start: 2,
+                        end: 3,
+                        line: 2,
+                        column: 1,
+                    },
+                    LexicalKind::UnexpectedCharacter('#'),
+                ),
             ];
             for error in errors {
                 writeln!(buf, "{error}").unwrap();
@@ -132,8 +333,24 @@ mod tests {
             let expected_err = {
                 let mut buf = Vec::new();
                 let errors = [
-                    Error::lexical(3, LexicalKind::UnexpectedCharacter('$')),
-                    Error::lexical(5, LexicalKind::UnexpectedCharacter('#')),
+                    Error::lexical(
+                        Span {
+                            start: 4,
+                            end: 5,
+                            line: 3,
+                            column: 1,
+                        },
+                        LexicalKind::UnexpectedCharacter('$'),
+                    ),
+                    Error::lexical(
+                        Span {
+                            start: 8,
+                            end: 9,
+                            line: 5,
+                            column: 1,
+                        },
+                        LexicalKind::UnexpectedCharacter('#'),
+                    ),
                 ];
                 for error in errors {
                     writeln!(buf, "{error}").unwrap();
@@ -143,4 +360,126 @@ mod tests {
             assert_eq!(err_buf, expected_err);
         }
     }
+
+    #[test]
+    fn jsonl() {
+        let mut buf = Vec::new();
+
+        let input = "var x = \"a\";\n$ 12";
+
+        let mut sink = io::sink();
+        let mut tokenizer = Tokenizer::new(input, &mut buf, &mut sink).with_format(Format::Jsonl);
+        tokenizer.tokenize().unwrap();
+        assert_eq!(tokenizer.outcome(), Outcome::LexicalErrors);
+
+        let lines = String::from_utf8(buf).unwrap();
+        let lines = lines.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 8);
+        assert_eq!(
+            lines[3],
+            r#"{"token":{"type":"STRING","lexeme":"\"a\"","literal":"a","span":{"start":8,"end":11,"line":1,"column":9}}}"#
+        );
+        assert_eq!(
+            lines[5],
+            r#"{"error":{"code":"L0001","kind":{"type":"UnexpectedCharacter","value":"$"},"message":"[line 2] Error: Unexpected character: $","span":{"start":13,"end":14,"line":2,"column":1}}}"#
+        );
+        assert_eq!(
+            lines[6],
+            r#"{"token":{"type":"NUMBER","lexeme":"12","literal":12.0,"span":{"start":15,"end":17,"line":2,"column":3}}}"#
+        );
+        assert_eq!(
+            lines[7],
+            r#"{"token":{"type":"EOF","lexeme":"","literal":null,"span":{"start":17,"end":17,"line":2,"column":5}}}"#
+        );
+    }
+
+    #[test]
+    fn max_errors() {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut tokenizer = Tokenizer::new("$ 1 # 2 @", &mut out, &mut err).with_max_errors(2);
+        tokenizer.tokenize().unwrap();
+        assert_eq!(tokenizer.outcome(), Outcome::LexicalErrors);
+        assert_eq!(tokenizer.errors(), 3);
+        assert_eq!(String::from_utf8(err).unwrap().lines().count(), 2);
+        assert_eq!(String::from_utf8(out).unwrap().lines().count(), 3);
+    }
+
+    #[test]
+    fn recovery() {
+        let input = "@@ \"open\n1";
+        let errors = |format| {
+            let (mut out, mut err) = (Vec::new(), Vec::new());
+            let mut tokenizer = Tokenizer::new(input, &mut out, &mut err).with_format(format);
+            tokenizer.tokenize().unwrap();
+            tokenizer.errors()
+        };
+        // Plain text reports each character and lets the string run to the end.
+        assert_eq!(errors(Format::Text), 3);
+        assert_eq!(errors(Format::Jsonl), 2);
+
+        let mut out = Vec::new();
+        Tokenizer::new(input, &mut out, &mut io::sink())
+            .with_diagnostics(Style::Rich, "test.lox")
+            .tokenize()
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "NUMBER 1 1.0\nEOF  null\n");
+    }
+
+    #[test]
+    fn fail_fast() {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let mut tokenizer = Tokenizer::new("1 $ 2 #", &mut out, &mut err)
+            .with_format(Format::Jsonl)
+            .with_fail_fast();
+        tokenizer.tokenize().unwrap();
+        assert_eq!(tokenizer.outcome().code(), 65);
+        assert_eq!(tokenizer.errors(), 1);
+        let out = String::from_utf8(out).unwrap();
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].starts_with(r#"{"error":"#));
+    }
+
+    #[test]
+    fn io_failure() {
+        struct Broken;
+
+        impl Write for Broken {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::BrokenPipe.into())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let (mut broken, mut out, mut err) = (Broken, io::sink(), io::sink());
+        let mut tokenizer = Tokenizer::new("1", &mut broken, &mut err);
+        assert!(tokenizer.tokenize().is_err());
+        assert_eq!(tokenizer.outcome(), Outcome::IoFailure);
+        assert_eq!(tokenizer.outcome().code(), 74);
+
+        let mut tokenizer = Tokenizer::new("1", &mut out, &mut err);
+        tokenizer.tokenize().unwrap();
+        assert_eq!(tokenizer.outcome(), Outcome::Success);
+        assert_eq!(tokenizer.errors(), 0);
+    }
+
+    #[test]
+    fn json() {
+        let mut buf = Vec::new();
+
+        Tokenizer::new("(", &mut buf, &mut io::sink())
+            .with_format(Format::Json)
+            .tokenize()
+            .unwrap();
+
+        let value: serde_json::Value = serde_json::from_slice(&buf).unwrap();
+        assert_eq!(value.as_array().unwrap().len(), 2);
+        assert_eq!(value[0]["token"]["type"], "LEFT_PAREN");
+        assert_eq!(value[1]["token"]["type"], "EOF");
+    }
 }
@@ -0,0 +1,111 @@
+use std::{fmt::Display, rc::Rc};
+
+use crate::{
+    callable::{Closure, Native},
+    class::{Class, Instance},
+    Literal,
+};
+
+#[derive(Debug, Clone)]
+pub enum Value<'a> {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    String(Rc<str>),
+    Closure(Rc<Closure<'a>>),
+    Native(Native),
+    Class(Rc<Class<'a>>),
+    Instance(Rc<Instance<'a>>),
+}
+
+impl Value<'_> {
+    /// `false` and `nil` are falsey, everything else is truthy.
+    #[inline]
+    pub(crate) const fn is_truthy(&self) -> bool {
+        !matches!(self, Self::Nil | Self::Bool(false))
+    }
+}
+
+impl PartialEq for Value<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Nil, Self::Nil) => true,
+            (Self::Bool(l), Self::Bool(r)) => l == r,
+            (Self::Number(l), Self::Number(r)) => l == r,
+            (Self::String(l), Self::String(r)) => l == r,
+            (Self::Closure(l), Self::Closure(r)) => Rc::ptr_eq(l, r),
+            (Self::Native(l), Self::Native(r)) => l.name == r.name,
+            (Self::Class(l), Self::Class(r)) => Rc::ptr_eq(l, r),
+            (Self::Instance(l), Self::Instance(r)) => Rc::ptr_eq(l, r),
+            _ => false,
+        }
+    }
+}
+
+impl From<&Literal<'_>> for Value<'_> {
+    fn from(literal: &Literal) -> Self {
+        match literal {
+            Literal::Null => Self::Nil,
+            Literal::String(x) => Self::String(Rc::from(&**x)),
+            Literal::Number(x) => Self::Number(*x),
+        }
+    }
+}
+
+impl From<&str> for Value<'_> {
+    fn from(x: &str) -> Self {
+        Self::String(Rc::from(x))
+    }
+}
+
+impl From<bool> for Value<'_> {
+    fn from(x: bool) -> Self {
+        Self::Bool(x)
+    }
+}
+
+impl From<f64> for Value<'_> {
+    fn from(x: f64) -> Self {
+        Self::Number(x)
+    }
+}
+
+impl Display for Value<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Nil => write!(f, "nil"),
+            Self::Bool(x) => x.fmt(f),
+            Self::Number(x) => x.fmt(f),
+            Self::String(x) => x.fmt(f),
+            Self::Closure(x) => x.fmt(f),
+            Self::Native(x) => x.fmt(f),
+            Self::Class(x) => x.fmt(f),
+            Self::Instance(x) => x.fmt(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn display() {
+        assert_eq!(Value::Nil.to_string(), "nil");
+        assert_eq!(Value::Bool(true).to_string(), "true");
+        assert_eq!(Value::Number(10.0).to_string(), "10");
+        assert_eq!(Value::Number(-0.5).to_string(), "-0.5");
+        assert_eq!(Value::String(Rc::from("foo")).to_string(), "foo");
+        assert_eq!(Value::Native(Native::CLOCK).to_string(), "<native fn>");
+    }
+
+    #[test]
+    fn truthiness() {
+        assert!(!Value::Nil.is_truthy());
+        assert!(!Value::Bool(false).is_truthy());
+        assert!(Value::Bool(true).is_truthy());
+        assert!(Value::Number(0.0).is_truthy());
+        assert!(Value::String(Rc::from("")).is_truthy());
+    }
+}
@@ -0,0 +1,743 @@
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    rc::Rc,
+};
+
+use crate::interpreter::{Error, RuntimeKind};
+
+mod chunk;
+use chunk::{Chunk, OpCode};
+
+mod compiler;
+use compiler::Compiler;
+pub use compiler::Errors;
+
+mod disassemble;
+
+mod heap;
+use heap::{BoundMethod, Class, Closure, Heap, Instance, Native, Obj, ObjRef, Upvalue};
+
+mod value;
+use value::Value;
+
+/// Maximum depth of calls, the top-level code included, which the tree-walker abides by too.
+pub const FRAMES_MAX: usize = 1 << 14;
+
+/// A function being run.
+#[derive(Debug)]
+struct Frame {
+    closure: ObjRef,
+    chunk: Rc<Chunk>,
+    ip: usize,
+    /// Stack slot of the callee, followed by the arguments and then the locals.
+    base: usize,
+}
+
+impl Frame {
+    #[inline]
+    fn byte(&mut self) -> u8 {
+        let byte = self.chunk.code[self.ip];
+        self.ip += 1;
+        byte
+    }
+
+    #[inline]
+    fn short(&mut self) -> u16 {
+        u16::from_be_bytes([self.byte(), self.byte()])
+    }
+
+    #[inline]
+    fn constant(&mut self) -> Value {
+        let index = self.short();
+        self.chunk.constants[usize::from(index)]
+    }
+
+    /// A constant holding a string object.
+    #[inline]
+    fn name(&mut self) -> ObjRef {
+        self.constant().as_obj().expect("names are strings")
+    }
+}
+
+/// The parts of a [`Vm`] the running program can reach objects from.
+struct Roots<'v> {
+    stack: &'v [Value],
+    frames: &'v [Frame],
+    running: Option<ObjRef>,
+    globals: &'v HashMap<ObjRef, Value>,
+    open_upvalues: &'v [(usize, ObjRef)],
+    init: ObjRef,
+}
+
+impl Roots<'_> {
+    fn values(&self) -> impl Iterator<Item = Value> + '_ {
+        let frames = self.frames.iter().map(|x| x.closure).chain(self.running);
+        let upvalues = self.open_upvalues.iter().map(|&(_, x)| x);
+        let globals = self.globals.iter().flat_map(|(&k, &v)| [Value::from(k), v]);
+        self.stack
+            .iter()
+            .copied()
+            .chain(globals)
+            .chain(frames.chain(upvalues).chain([self.init]).map(Value::from))
+    }
+
+    fn mark(&self, heap: &mut Heap) {
+        for value in self.values() {
+            heap.mark(value);
+        }
+    }
+}
+
+/// Runs programs compiled to bytecode, writing what they print to `out`.
+pub struct Vm<W> {
+    heap: Heap,
+    stack: Vec<Value>,
+    /// The callers of the running function.
+    frames: Vec<Frame>,
+    /// The closure of the running function, kept alive along with those of `frames`.
+    running: Option<ObjRef>,
+    globals: HashMap<ObjRef, Value>,
+    /// Upvalues still pointing into the stack, sorted by their slot.
+    open_upvalues: Vec<(usize, ObjRef)>,
+    /// The name of initializers.
+    init: ObjRef,
+    out: W,
+}
+
+impl<W> Vm<W>
+where
+    W: Write,
+{
+    #[must_use]
+    pub fn new(out: W) -> Self {
+        let mut heap = Heap::default();
+        let mut globals = HashMap::new();
+        for native in Native::ALL {
+            let name = heap.intern(native.name);
+            globals.insert(name, Value::from(heap.alloc(Obj::Native(native))));
+        }
+        Self {
+            init: heap.intern("init"),
+            heap,
+            stack: Vec::new(),
+            frames: Vec::new(),
+            running: None,
+            globals,
+            open_upvalues: Vec::new(),
+            out,
+        }
+    }
+
+    /// Collects garbage before every allocation rather than as the heap grows.
+    #[must_use]
+    pub fn with_gc_stress(mut self) -> Self {
+        self.heap.set_stress(true);
+        self
+    }
+
+    /// Compiles `input` into the function running its top-level code.
+    pub fn compile(&mut self, input: &str) -> Result<ObjRef, Errors> {
+        let (heap, roots) = self.split();
+        let roots = roots.values().collect();
+        Compiler::new(input, heap, roots).compile()
+    }
+
+    /// Writes out the bytecode of `function` and of the functions it defines.
+    pub fn disassemble(&self, function: ObjRef, out: &mut impl Write) -> io::Result<()> {
+        disassemble::disassemble(&self.heap, function, out)
+    }
+
+    /// Runs the top-level `function` returned by [`Vm::compile`].
+    pub fn interpret(&mut self, function: ObjRef) -> Result<(), Error> {
+        let closure = self.alloc(Obj::Closure(Closure {
+            function,
+            upvalues: Vec::new(),
+        }));
+        self.stack.push(Value::from(closure));
+        self.running = Some(closure);
+        let frame = Frame {
+            closure,
+            chunk: Rc::clone(&self.heap.function(function).chunk),
+            ip: 0,
+            base: 0,
+        };
+
+        let result = self.run(frame);
+        self.running = None;
+        if result.is_err() {
+            self.stack.clear();
+            self.frames.clear();
+            self.open_upvalues.clear();
+        }
+        self.out.flush()?;
+        result
+    }
+
+    fn run(&mut self, mut frame: Frame) -> Result<(), Error> {
+        loop {
+            let offset = frame.ip;
+            let op = OpCode::from_repr(frame.byte()).expect("valid bytecode");
+            let result = match op {
+                OpCode::Constant => {
+                    let value = frame.constant();
+                    self.stack.push(value);
+                    Ok(())
+                }
+                OpCode::Nil => {
+                    self.stack.push(Value::NIL);
+                    Ok(())
+                }
+                OpCode::True => {
+                    self.stack.push(Value::from(true));
+                    Ok(())
+                }
+                OpCode::False => {
+                    self.stack.push(Value::from(false));
+                    Ok(())
+                }
+                OpCode::Pop => {
+                    self.pop();
+                    Ok(())
+                }
+                OpCode::CloseUpvalue => {
+                    self.close_upvalues(self.stack.len() - 1);
+                    self.pop();
+                    Ok(())
+                }
+                OpCode::GetLocal
+                | OpCode::SetLocal
+                | OpCode::GetUpvalue
+                | OpCode::SetUpvalue
+                | OpCode::GetGlobal
+                | OpCode::DefineGlobal
+                | OpCode::SetGlobal => self.variable(&mut frame, op),
+                OpCode::GetProperty | OpCode::SetProperty | OpCode::GetSuper => {
+                    self.property(&mut frame, op)
+                }
+                OpCode::Not => {
+                    let value = self.pop();
+                    self.stack.push(Value::from(value.is_falsey()));
+                    Ok(())
+                }
+                OpCode::Negate => {
+                    let value = self.pop().as_number();
+                    value
+                        .map(|x| self.stack.push(Value::from(-x)))
+                        .ok_or(RuntimeKind::OperandNotNumber)
+                }
+                OpCode::Equal
+                | OpCode::Greater
+                | OpCode::GreaterEqual
+                | OpCode::Less
+                | OpCode::LessEqual
+                | OpCode::Add
+                | OpCode::Subtract
+                | OpCode::Multiply
+                | OpCode::Divide => self.binary(op),
+                OpCode::Print => {
+                    let value = self.pop();
+                    writeln!(self.out, "{}", self.heap.display(value))?;
+                    Ok(())
+                }
+                OpCode::Jump | OpCode::JumpIfFalse | OpCode::Loop => {
+                    self.jump(&mut frame, op);
+                    Ok(())
+                }
+                OpCode::Call => {
+                    let count = frame.byte();
+                    self.call_value(usize::from(count)).map(|callee| {
+                        if let Some(callee) = callee {
+                            self.running = Some(callee.closure);
+                            self.frames.push(std::mem::replace(&mut frame, callee));
+                        }
+                    })
+                }
+                OpCode::Closure => {
+                    self.closure(&mut frame);
+                    Ok(())
+                }
+                OpCode::Return => {
+                    let result = self.pop();
+                    self.close_upvalues(frame.base);
+                    self.stack.truncate(frame.base);
+                    let Some(caller) = self.frames.pop() else {
+                        return Ok(());
+                    };
+                    self.stack.push(result);
+                    self.running = Some(caller.closure);
+                    frame = caller;
+                    Ok(())
+                }
+                OpCode::Class | OpCode::Inherit | OpCode::Method => self.class(&mut frame, op),
+            };
+            if let Err(kind) = result {
+                return Err(Error::runtime(frame.chunk.line(offset), kind));
+            }
+        }
+    }
+
+    /// Allocates `obj`, collecting garbage first if the heap has grown enough.
+    fn alloc(&mut self, obj: Obj) -> ObjRef {
+        let (heap, roots) = self.split();
+        heap.alloc_with(obj, |heap| roots.mark(heap))
+    }
+
+    /// Like [`Heap::intern`], collecting garbage first if a string gets allocated.
+    fn intern(&mut self, string: &str) -> ObjRef {
+        let (heap, roots) = self.split();
+        heap.intern_with(string, |heap| roots.mark(heap))
+    }
+
+    /// The heap, along with what the running program can still reach on it.
+    fn split(&mut self) -> (&mut Heap, Roots<'_>) {
+        let roots = Roots {
+            stack: &self.stack,
+            frames: &self.frames,
+            running: self.running,
+            globals: &self.globals,
+            open_upvalues: &self.open_upvalues,
+            init: self.init,
+        };
+        (&mut self.heap, roots)
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("stack underflow")
+    }
+
+    #[inline]
+    fn peek(&self, distance: usize) -> Value {
+        self.stack[self.stack.len() - 1 - distance]
+    }
+
+    fn jump(&self, frame: &mut Frame, op: OpCode) {
+        let jump = usize::from(frame.short());
+        match op {
+            OpCode::Jump => frame.ip += jump,
+            OpCode::JumpIfFalse if self.peek(0).is_falsey() => frame.ip += jump,
+            OpCode::JumpIfFalse => (),
+            OpCode::Loop => frame.ip -= jump,
+            _ => unreachable!("not a jump instruction: {op}"),
+        }
+    }
+
+    fn variable(&mut self, frame: &mut Frame, op: OpCode) -> Result<(), RuntimeKind> {
+        match op {
+            OpCode::GetLocal => {
+                let slot = frame.base + usize::from(frame.byte());
+                self.stack.push(self.stack[slot]);
+            }
+            OpCode::SetLocal => {
+                let slot = frame.base + usize::from(frame.byte());
+                self.stack[slot] = self.peek(0);
+            }
+            OpCode::GetUpvalue => {
+                let index = usize::from(frame.byte());
+                let upvalue = self.heap.closure(frame.closure).upvalues[index];
+                let value = match self.heap.upvalue(upvalue) {
+                    Upvalue::Open(slot) => self.stack[slot],
+                    Upvalue::Closed(value) => value,
+                };
+                self.stack.push(value);
+            }
+            OpCode::SetUpvalue => {
+                let index = usize::from(frame.byte());
+                let upvalue = self.heap.closure(frame.closure).upvalues[index];
+                let value = self.peek(0);
+                match self.heap.upvalue_mut(upvalue) {
+                    Upvalue::Open(slot) => self.stack[*slot] = value,
+                    Upvalue::Closed(x) => *x = value,
+                }
+            }
+            OpCode::GetGlobal => {
+                let name = frame.name();
+                let value = *self
+                    .globals
+                    .get(&name)
+                    .ok_or_else(|| self.undefined(name))?;
+                self.stack.push(value);
+            }
+            OpCode::DefineGlobal => {
+                let name = frame.name();
+                let value = self.pop();
+                self.globals.insert(name, value);
+            }
+            OpCode::SetGlobal => {
+                let name = frame.name();
+                let value = self.peek(0);
+                if !self.globals.contains_key(&name) {
+                    return Err(self.undefined(name));
+                }
+                self.globals.insert(name, value);
+            }
+            _ => unreachable!("not a variable instruction: {op}"),
+        }
+        Ok(())
+    }
+
+    fn undefined(&self, name: ObjRef) -> RuntimeKind {
+        RuntimeKind::UndefinedVariable(self.heap.string(name).to_string())
+    }
+
+    fn property(&mut self, frame: &mut Frame, op: OpCode) -> Result<(), RuntimeKind> {
+        let name = frame.name();
+        match op {
+            OpCode::GetProperty => {
+                let instance = self
+                    .heap
+                    .as_instance(self.peek(0))
+                    .ok_or(RuntimeKind::NotInstanceProperties)?;
+                // Fields shadow methods.
+                if let Some(&value) = instance.fields.get(&name) {
+                    self.pop();
+                    self.stack.push(value);
+                    return Ok(());
+                }
+                let class = instance.class;
+                self.bind_method(class, name)
+            }
+            OpCode::SetProperty => {
+                let instance = self
+                    .peek(1)
+                    .as_obj()
+                    .filter(|&x| matches!(self.heap.get(x), Obj::Instance(_)))
+                    .ok_or(RuntimeKind::NotInstanceFields)?;
+                let value = self.pop();
+                self.heap.set_field(instance, name, value);
+                self.pop();
+                self.stack.push(value);
+                Ok(())
+            }
+            OpCode::GetSuper => {
+                let superclass = self.pop().as_obj().expect("'super' is a class");
+                self.bind_method(superclass, name)
+            }
+            _ => unreachable!("not a property instruction: {op}"),
+        }
+    }
+
+    /// Replaces the instance on top of the stack with its method `name` from `class`.
+    fn bind_method(&mut self, class: ObjRef, name: ObjRef) -> Result<(), RuntimeKind> {
+        let method = self
+            .heap
+            .as_class(Value::from(class))
+            .and_then(|class| class.methods.get(&name).copied())
+            .ok_or_else(|| RuntimeKind::UndefinedProperty(self.heap.string(name).to_string()))?;
+        let receiver = self.pop();
+        let bound = self.alloc(Obj::BoundMethod(BoundMethod { receiver, method }));
+        self.stack.push(Value::from(bound));
+        Ok(())
+    }
+
+    fn binary(&mut self, op: OpCode) -> Result<(), RuntimeKind> {
+        let right = self.pop();
+        let left = self.pop();
+        if op == OpCode::Equal {
+            self.stack.push(Value::from(left == right));
+            return Ok(());
+        }
+
+        let (Some(l), Some(r)) = (left.as_number(), right.as_number()) else {
+            if op != OpCode::Add {
+                return Err(RuntimeKind::OperandsNotNumbers);
+            }
+            let (Some(l), Some(r)) = (self.heap.as_string(left), self.heap.as_string(right)) else {
+                return Err(RuntimeKind::OperandsNotNumbersOrStrings);
+            };
+            let string = format!("{l}{r}");
+            let string = self.intern(&string);
+            self.stack.push(Value::from(string));
+            return Ok(());
+        };
+        let value = match op {
+            OpCode::Greater => Value::from(l > r),
+            OpCode::GreaterEqual => Value::from(l >= r),
+            OpCode::Less => Value::from(l < r),
+            OpCode::LessEqual => Value::from(l <= r),
+            OpCode::Add => Value::from(l + r),
+            OpCode::Subtract => Value::from(l - r),
+            OpCode::Multiply => Value::from(l * r),
+            OpCode::Divide => Value::from(l / r),
+            _ => unreachable!("not a binary instruction: {op}"),
+        };
+        self.stack.push(value);
+        Ok(())
+    }
+
+    /// Calls the value below `count` arguments on the stack, returning the frame to continue
+    /// in unless the call already completed.
+    fn call_value(&mut self, count: usize) -> Result<Option<Frame>, RuntimeKind> {
+        let slot = self.stack.len() - count - 1;
+        let Some(callee) = self.stack[slot].as_obj() else {
+            return Err(RuntimeKind::NotCallable);
+        };
+        match self.heap.get(callee) {
+            Obj::Closure(_) => self.call(callee, count).map(Some),
+            Obj::BoundMethod(x) => {
+                let method = x.method;
+                self.stack[slot] = x.receiver;
+                self.call(method, count).map(Some)
+            }
+            Obj::Class(x) => {
+                let init = x.methods.get(&self.init).copied();
+                let instance = self.alloc(Obj::Instance(Instance {
+                    class: callee,
+                    fields: HashMap::new(),
+                }));
+                self.stack[slot] = Value::from(instance);
+                match init {
+                    Some(init) => self.call(init, count).map(Some),
+                    None if count == 0 => Ok(None),
+                    None => Err(RuntimeKind::Arity {
+                        expected: 0,
+                        got: count,
+                    }),
+                }
+            }
+            &Obj::Native(native) => {
+                if count != native.arity {
+                    return Err(RuntimeKind::Arity {
+                        expected: native.arity,
+                        got: count,
+                    });
+                }
+                let result = (native.fun)(&self.stack[slot + 1..]);
+                self.stack.truncate(slot);
+                self.stack.push(result);
+                Ok(None)
+            }
+            _ => Err(RuntimeKind::NotCallable),
+        }
+    }
+
+    fn call(&self, closure: ObjRef, count: usize) -> Result<Frame, RuntimeKind> {
+        let function = self.heap.function(self.heap.closure(closure).function);
+        if count != function.arity {
+            return Err(RuntimeKind::Arity {
+                expected: function.arity,
+                got: count,
+            });
+        }
+        if self.frames.len() + 1 >= FRAMES_MAX {
+            return Err(RuntimeKind::StackOverflow);
+        }
+        Ok(Frame {
+            closure,
+            chunk: Rc::clone(&function.chunk),
+            ip: 0,
+            base: self.stack.len() - count - 1,
+        })
+    }
+
+    fn closure(&mut self, frame: &mut Frame) {
+        let function = frame
+            .constant()
+            .as_obj()
+            .expect("closures are of functions");
+        let count = self.heap.function(function).upvalues;
+        let mut upvalues = Vec::with_capacity(count);
+        for _ in 0..count {
+            let is_local = frame.byte() != 0;
+            let index = usize::from(frame.byte());
+            let upvalue = if is_local {
+                self.capture_upvalue(frame.base + index)
+            } else {
+                self.heap.closure(frame.closure).upvalues[index]
+            };
+            upvalues.push(upvalue);
+        }
+        let closure = self.alloc(Obj::Closure(Closure { function, upvalues }));
+        self.stack.push(Value::from(closure));
+    }
+
+    /// The upvalue for the local in `slot`, shared by every closure capturing it.
+    fn capture_upvalue(&mut self, slot: usize) -> ObjRef {
+        let index = self.open_upvalues.partition_point(|&(s, _)| s < slot);
+        if let Some(&(s, upvalue)) = self.open_upvalues.get(index) {
+            if s == slot {
+                return upvalue;
+            }
+        }
+        let upvalue = self.alloc(Obj::Upvalue(Upvalue::Open(slot)));
+        self.open_upvalues.insert(index, (slot, upvalue));
+        upvalue
+    }
+
+    /// Moves the captured locals from `slot` up off the stack.
+    fn close_upvalues(&mut self, slot: usize) {
+        while let Some(&(s, upvalue)) = self.open_upvalues.last() {
+            if s < slot {
+                break;
+            }
+            *self.heap.upvalue_mut(upvalue) = Upvalue::Closed(self.stack[s]);
+            self.open_upvalues.pop();
+        }
+    }
+
+    fn class(&mut self, frame: &mut Frame, op: OpCode) -> Result<(), RuntimeKind> {
+        match op {
+            OpCode::Class => {
+                let name = frame.name();
+                let class = self.alloc(Obj::Class(Class {
+                    name,
+                    methods: HashMap::new(),
+                }));
+                self.stack.push(Value::from(class));
+            }
+            OpCode::Inherit => {
+                let methods = self
+                    .heap
+                    .as_class(self.peek(1))
+                    .ok_or(RuntimeKind::SuperclassNotClass)?
+                    .methods
+                    .clone();
+                let subclass = self.pop().as_obj().expect("subclasses are classes");
+                self.heap.add_methods(subclass, methods);
+            }
+            OpCode::Method => {
+                let name = frame.name();
+                let method = self.pop().as_obj().expect("methods are closures");
+                let class = self.peek(0).as_obj().expect("methods belong to classes");
+                self.heap.add_methods(class, [(name, method)]);
+            }
+            _ => unreachable!("not a class instruction: {op}"),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    fn run(input: &str) -> String {
+        let mut out = Vec::new();
+        let mut vm = Vm::new(&mut out).with_gc_stress();
+        let function = vm.compile(input).unwrap();
+        vm.interpret(function).unwrap();
+        assert!(vm.open_upvalues.is_empty());
+        String::from_utf8(out).unwrap()
+    }
+
+    /// Runs `input`, then collects garbage, returning how many objects are left.
+    fn live_after(input: &str) -> usize {
+        let mut vm = Vm::new(io::sink());
+        let function = vm.compile(input).unwrap();
+        vm.interpret(function).unwrap();
+        let (heap, roots) = vm.split();
+        heap.collect_with(|heap| roots.mark(heap));
+        heap.live()
+    }
+
+    #[test]
+    fn counter() {
+        let input = "\
+        fun counter() {\n\
+            var n = 0;\n\
+            fun count() { n = n + 1; return n; }\n\
+            return count;\n\
+        }\n\
+        var a = counter();\n\
+        var b = counter();\n\
+        a(); a();\n\
+        print a();\n\
+        print b();\n\
+        ";
+        assert_eq!(run(input), "3\n1\n");
+    }
+
+    #[test]
+    fn shared() {
+        let input = "\
+        var get; var set;\n\
+        {\n\
+            var x = 1;\n\
+            fun g() { return x; }\n\
+            fun s(v) { x = v; }\n\
+            get = g; set = s;\n\
+            set(2);\n\
+            print x;\n\
+        }\n\
+        set(3);\n\
+        print get();\n\
+        ";
+        assert_eq!(run(input), "2\n3\n");
+    }
+
+    #[test]
+    fn loop_capture() {
+        let input = "\
+        var a; var b; var c; var d;\n\
+        for (var i = 0; i < 2; i = i + 1) {\n\
+            var j = i;\n\
+            fun fi() { return i; }\n\
+            fun fj() { return j; }\n\
+            if (i == 0) { a = fi; b = fj; } else { c = fi; d = fj; }\n\
+        }\n\
+        print a(); print b(); print c(); print d();\n\
+        ";
+        // The loop variable is shared by all iterations, the body's are not.
+        assert_eq!(run(input), "2\n0\n2\n1\n");
+    }
+
+    #[test]
+    fn close_on_scope_exit() {
+        let input = "\
+        var f;\n\
+        {\n\
+            var a = \"a\";\n\
+            { var b = \"b\"; fun g() { return a + b; } f = g; }\n\
+            var c = \"c\";\n\
+            print f();\n\
+        }\n\
+        { var d = \"d\"; var e = \"e\"; print f(); }\n\
+        ";
+        assert_eq!(run(input), "ab\nab\n");
+    }
+
+    #[test]
+    fn collect_garbage() {
+        let empty = live_after("");
+        let garbage = "\
+        class A { m() { return this; } }\n\
+        for (var i = 0; i < 100; i = i + 1) {\n\
+            var s = \"s\" + \"t\";\n\
+            fun f() { return s; }\n\
+            A().m;\n\
+        }\n\
+        ";
+        // The global class, its method closure and function, and their names.
+        assert_eq!(live_after(garbage) - empty, 5);
+    }
+
+    #[test]
+    fn count_growth() {
+        // Collections check that the size of every live object is up to date.
+        let input = "\
+        class A { f() {} g() {} }\n\
+        class B < A { g() {} h() {} }\n\
+        var b = B();\n\
+        for (var i = 0; i < 10; i = i + 1) { b.x = i; b.y = \"y\" + \"\"; }\n\
+        print b.x;\n\
+        ";
+        assert_eq!(run(input), "9\n");
+    }
+
+    #[test]
+    fn keep_reachable() {
+        let input = "\
+        class List { init(head, tail) { this.head = head; this.tail = tail; } }\n\
+        var list = nil;\n\
+        for (var i = 0; i < 3; i = i + 1) list = List(\"item\" + \"\", list);\n\
+        fun last() { var x = list; while (x.tail != nil) x = x.tail; return x.head; }\n\
+        var method = List(1, nil).init;\n\
+        print last();\n\
+        print method(2, nil).head;\n\
+        ";
+        assert_eq!(run(input), "item\n2\n");
+    }
+}
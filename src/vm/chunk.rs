@@ -0,0 +1,110 @@
+use super::Value;
+
+/// Instructions of the virtual machine, each followed by the operands listed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum_macros::FromRepr, strum_macros::Display)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+#[repr(u8)]
+pub enum OpCode {
+    /// Constant index, two bytes.
+    Constant,
+    Nil,
+    True,
+    False,
+    Pop,
+    /// Stack slot, one byte.
+    GetLocal,
+    SetLocal,
+    /// Constant index of the name, two bytes.
+    GetGlobal,
+    DefineGlobal,
+    SetGlobal,
+    /// Upvalue index, one byte.
+    GetUpvalue,
+    SetUpvalue,
+    /// Constant index of the name, two bytes.
+    GetProperty,
+    SetProperty,
+    GetSuper,
+    Equal,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Not,
+    Negate,
+    Print,
+    /// Forward offset, two bytes.
+    Jump,
+    JumpIfFalse,
+    /// Backward offset, two bytes.
+    Loop,
+    /// Argument count, one byte.
+    Call,
+    /// Constant index of the function, two bytes, then a local flag and an index byte for each
+    /// of its upvalues.
+    Closure,
+    /// Moves the local on top of the stack off it, into the upvalue capturing it.
+    CloseUpvalue,
+    Return,
+    /// Constant index of the name, two bytes.
+    Class,
+    Inherit,
+    Method,
+}
+
+/// Compiled code of a single function.
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub code: Vec<u8>,
+    pub constants: Vec<Value>,
+    /// Source line of every byte of `code`, run-length encoded as `(line, count)`.
+    lines: Vec<(usize, usize)>,
+}
+
+impl Chunk {
+    pub fn write(&mut self, byte: u8, line: usize) {
+        self.code.push(byte);
+        match self.lines.last_mut() {
+            Some((last, count)) if *last == line => *count += 1,
+            _ => self.lines.push((line, 1)),
+        }
+    }
+
+    pub fn add_constant(&mut self, value: Value) -> usize {
+        self.constants.push(value);
+        self.constants.len() - 1
+    }
+
+    /// Source line of the byte at `offset`.
+    pub fn line(&self, offset: usize) -> usize {
+        let mut start = 0;
+        for &(line, count) in &self.lines {
+            start += count;
+            if offset < start {
+                return line;
+            }
+        }
+        self.lines.last().map_or(0, |&(line, _)| line)
+    }
+
+    /// Drops the code past `len` bytes.
+    pub fn truncate(&mut self, len: usize) {
+        let mut excess = self.code.len() - len;
+        self.code.truncate(len);
+        while excess > 0 {
+            let Some((_, count)) = self.lines.last_mut() else {
+                break;
+            };
+            if *count > excess {
+                *count -= excess;
+                break;
+            }
+            excess -= *count;
+            self.lines.pop();
+        }
+    }
+}
@@ -0,0 +1,1072 @@
+use std::{borrow::Cow, rc::Rc};
+
+use crate::{
+    parser::{Error, FunctionKind, SyntaxKind, MAX_ARGS},
+    resolver::{self, SemanticKind},
+    Literal, Scanner, Token, Type,
+};
+
+use super::{
+    heap::{Function, Obj},
+    Chunk, Heap, ObjRef, OpCode, Value,
+};
+
+/// Maximum number of locals in scope in a function, and of variables it captures.
+const MAX_LOCALS: usize = 256;
+
+/// Errors that keep a program from compiling, in the order the tree-walker reports them.
+#[derive(Debug)]
+pub enum Errors {
+    /// Lexical and syntax errors, which are all the parser would report.
+    Syntax(Vec<Error>),
+    /// Errors the resolver would report, given that the syntax is valid.
+    Semantic(Vec<resolver::Error>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FunctionType {
+    Script,
+    Function,
+    Initializer,
+    Method,
+}
+
+struct Local<'a> {
+    name: Cow<'a, str>,
+    /// Scope depth, `None` until the variable is initialized.
+    depth: Option<usize>,
+    /// Whether a closure captures the variable, which then has to be closed over when it goes
+    /// out of scope.
+    captured: bool,
+}
+
+/// A variable captured by a closure, either a local of the enclosing function or one of its
+/// own upvalues.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Upvalue {
+    index: u8,
+    is_local: bool,
+}
+
+/// A function being compiled.
+struct State<'a> {
+    ty: FunctionType,
+    name: Option<ObjRef>,
+    arity: usize,
+    chunk: Chunk,
+    /// By stack slot, starting with the callee itself.
+    locals: Vec<Local<'a>>,
+    upvalues: Vec<Upvalue>,
+    scope_depth: usize,
+}
+
+impl State<'_> {
+    fn new(ty: FunctionType, name: Option<ObjRef>) -> Self {
+        // Methods find their receiver in the callee's slot.
+        let slot = match ty {
+            FunctionType::Initializer | FunctionType::Method => "this",
+            FunctionType::Script | FunctionType::Function => "",
+        };
+        Self {
+            ty,
+            name,
+            arity: 0,
+            chunk: Chunk::default(),
+            locals: vec![Local {
+                name: Cow::Borrowed(slot),
+                depth: Some(0),
+                captured: false,
+            }],
+            upvalues: Vec::new(),
+            scope_depth: 0,
+        }
+    }
+}
+
+struct ClassState {
+    has_superclass: bool,
+}
+
+/// Where a variable lives.
+#[derive(Debug, Clone, Copy)]
+enum Slot {
+    Local(u8),
+    Upvalue(u8),
+    Global(u16),
+}
+
+/// What an expression just compiled would assign to if it was followed by `=`.
+enum Target<'a> {
+    None,
+    /// Read by the code from `start`, with `errors` semantic errors before the read.
+    Variable {
+        start: usize,
+        name: Cow<'a, str>,
+        line: usize,
+        errors: usize,
+    },
+    /// Read by the code from `start`, with `errors` semantic errors before the object.
+    Property {
+        start: usize,
+        name: u16,
+        line: usize,
+        errors: usize,
+    },
+}
+
+/// Compiles source code straight to bytecode in a single pass, following the grammar of the
+/// [`Parser`](crate::Parser) and the checks of the [`Resolver`](crate::Resolver).
+pub struct Compiler<'a, 'h> {
+    scanner: Scanner<'a>,
+    previous: Token<'a>,
+    current: Token<'a>,
+    heap: &'h mut Heap,
+    /// What the virtual machine holds on to, kept alive by collections while compiling.
+    roots: Vec<Value>,
+    /// The innermost function last.
+    functions: Vec<State<'a>>,
+    classes: Vec<ClassState>,
+    syntax: Vec<Error>,
+    semantic: Vec<resolver::Error>,
+}
+
+impl<'a, 'h> Compiler<'a, 'h> {
+    pub fn new(input: &'a str, heap: &'h mut Heap, roots: Vec<Value>) -> Self {
+        let mut compiler = Self {
+            scanner: Scanner::new(input),
+            previous: Token::EOF,
+            current: Token::EOF,
+            heap,
+            roots,
+            functions: vec![State::new(FunctionType::Script, None)],
+            classes: Vec::new(),
+            syntax: Vec::new(),
+            semantic: Vec::new(),
+        };
+        compiler.advance();
+        compiler
+    }
+
+    /// Compiles the whole program into the function running its top-level code.
+    pub fn compile(mut self) -> Result<ObjRef, Errors> {
+        while self.current.ty != Type::Eof {
+            if let Err(e) = self.declaration() {
+                self.syntax.push(e);
+                self.synchronize();
+                self.recover();
+            }
+        }
+        let (function, _) = self.end_function();
+
+        if !self.syntax.is_empty() {
+            Err(Errors::Syntax(self.syntax))
+        } else if !self.semantic.is_empty() {
+            Err(Errors::Semantic(self.semantic))
+        } else {
+            Ok(function)
+        }
+    }
+
+    /// Discards tokens until the start of the next statement.
+    fn synchronize(&mut self) {
+        while self.current.ty != Type::Eof {
+            let previous = self.advance();
+            if previous.ty == Type::Semicolon {
+                return;
+            }
+            if matches!(
+                self.current.ty,
+                Type::Class
+                    | Type::Fun
+                    | Type::Var
+                    | Type::For
+                    | Type::If
+                    | Type::While
+                    | Type::Print
+                    | Type::Return
+            ) {
+                return;
+            }
+        }
+    }
+
+    /// Leaves whatever functions, classes and scopes an error unwound out of.
+    fn recover(&mut self) {
+        self.functions.truncate(1);
+        self.classes.clear();
+        let script = &mut self.functions[0];
+        script.locals.truncate(1);
+        script.scope_depth = 0;
+    }
+
+    /// Moves to the next valid token, collecting lexical errors along the way.
+    fn advance(&mut self) -> Token<'a> {
+        let previous = self.current.clone();
+        for token in self.scanner.by_ref() {
+            match token {
+                Ok(token) => {
+                    self.current = token;
+                    break;
+                }
+                Err(e) => self.syntax.push(e.into()),
+            }
+        }
+        self.previous = previous.clone();
+        previous
+    }
+
+    fn next_if(&mut self, types: &[Type]) -> Option<Token<'a>> {
+        types.contains(&self.current.ty).then(|| self.advance())
+    }
+
+    fn expect(&mut self, ty: Type, kind: SyntaxKind) -> Result<Token<'a>, Error> {
+        self.next_if(&[ty]).ok_or_else(|| self.error(kind))
+    }
+
+    fn error(&self, kind: SyntaxKind) -> Error {
+        Error::syntax(&self.current, kind)
+    }
+
+    /// Reports a limit of the bytecode being exceeded, without unwinding.
+    fn limit(&mut self, kind: SyntaxKind) {
+        self.syntax.push(Error::syntax(&self.previous, kind));
+    }
+
+    fn semantic(&mut self, line: usize, lexeme: &str, kind: SemanticKind) {
+        self.semantic
+            .push(resolver::Error::semantic(line, lexeme, kind));
+    }
+
+    /// Line of the last token consumed.
+    const fn line(&self) -> usize {
+        self.previous.span.line
+    }
+
+    fn function(&mut self) -> &mut State<'a> {
+        self.functions
+            .last_mut()
+            .expect("the script is always compiling")
+    }
+
+    fn chunk(&mut self) -> &mut Chunk {
+        &mut self.function().chunk
+    }
+
+    fn emit(&mut self, op: OpCode, line: usize) {
+        self.emit_with(op, &[], line);
+    }
+
+    fn emit_with(&mut self, op: OpCode, operands: &[u8], line: usize) {
+        let chunk = self.chunk();
+        chunk.write(op as u8, line);
+        for &byte in operands {
+            chunk.write(byte, line);
+        }
+    }
+
+    fn make_constant(&mut self, value: Value) -> u16 {
+        let index = self.chunk().add_constant(value);
+        u16::try_from(index).unwrap_or_else(|_| {
+            self.limit(SyntaxKind::TooManyConstants);
+            0
+        })
+    }
+
+    fn constant(&mut self, value: Value, line: usize) {
+        let index = self.make_constant(value);
+        self.emit_with(OpCode::Constant, &index.to_be_bytes(), line);
+    }
+
+    fn identifier(&mut self, name: &str) -> u16 {
+        let name = self.intern(name);
+        self.make_constant(Value::from(name))
+    }
+
+    /// Allocates `obj`, collecting garbage first if the heap has grown enough.
+    fn alloc(&mut self, obj: Obj) -> ObjRef {
+        let (roots, functions) = (&self.roots, &self.functions);
+        self.heap
+            .alloc_with(obj, |heap| mark_roots(heap, roots, functions))
+    }
+
+    /// Like [`Heap::intern`], collecting garbage first if a string gets allocated.
+    fn intern(&mut self, string: &str) -> ObjRef {
+        let (roots, functions) = (&self.roots, &self.functions);
+        self.heap
+            .intern_with(string, |heap| mark_roots(heap, roots, functions))
+    }
+
+    /// Emits a jump to be patched, returning the offset of its operand.
+    fn emit_jump(&mut self, op: OpCode, line: usize) -> usize {
+        self.emit_with(op, &[0xff, 0xff], line);
+        self.chunk().code.len() - 2
+    }
+
+    /// Points the jump with its operand at `offset` to the end of the code.
+    fn patch_jump(&mut self, offset: usize) {
+        let jump = self.chunk().code.len() - offset - 2;
+        let jump = u16::try_from(jump).unwrap_or_else(|_| {
+            self.limit(SyntaxKind::JumpTooLarge);
+            u16::MAX
+        });
+        self.chunk().code[offset..offset + 2].copy_from_slice(&jump.to_be_bytes());
+    }
+
+    fn emit_loop(&mut self, start: usize, line: usize) {
+        let offset = self.chunk().code.len() + 3 - start;
+        let offset = u16::try_from(offset).unwrap_or_else(|_| {
+            self.limit(SyntaxKind::LoopTooLarge);
+            u16::MAX
+        });
+        self.emit_with(OpCode::Loop, &offset.to_be_bytes(), line);
+    }
+
+    fn emit_return(&mut self, line: usize) {
+        if self.function().ty == FunctionType::Initializer {
+            self.emit_with(OpCode::GetLocal, &[0], line);
+        } else {
+            self.emit(OpCode::Nil, line);
+        }
+        self.emit(OpCode::Return, line);
+    }
+
+    /// Finishes the innermost function, returning it along with the variables it captures.
+    fn end_function(&mut self) -> (ObjRef, Vec<Upvalue>) {
+        self.emit_return(self.line());
+        let state = self.functions.pop().expect("a function is compiling");
+        let function = self.alloc(Obj::Function(Function {
+            arity: state.arity,
+            upvalues: state.upvalues.len(),
+            chunk: Rc::new(state.chunk),
+            name: state.name,
+        }));
+        (function, state.upvalues)
+    }
+
+    fn begin_scope(&mut self) {
+        self.function().scope_depth += 1;
+    }
+
+    fn end_scope(&mut self) {
+        let line = self.line();
+        let state = self.function();
+        state.scope_depth -= 1;
+        let depth = state.scope_depth;
+        let count = state
+            .locals
+            .iter()
+            .rev()
+            .take_while(|local| local.depth.map_or(true, |x| x > depth))
+            .count();
+        let len = state.locals.len() - count;
+        let locals = state.locals.split_off(len);
+        for local in locals.iter().rev() {
+            let op = if local.captured {
+                OpCode::CloseUpvalue
+            } else {
+                OpCode::Pop
+            };
+            self.emit(op, line);
+        }
+    }
+
+    /// Declares a variable in the current scope, returning the constant naming it if it is a
+    /// global.
+    fn declare(&mut self, name: &Token<'a>) -> Option<u16> {
+        let state = self.function();
+        let depth = state.scope_depth;
+        if depth == 0 {
+            return Some(self.identifier(&name.lexeme));
+        }
+        let declared = state
+            .locals
+            .iter()
+            .rev()
+            .take_while(|local| local.depth.map_or(true, |x| x == depth))
+            .any(|local| local.name == name.lexeme);
+        if declared {
+            self.semantic(name.span.line, &name.lexeme, SemanticKind::AlreadyDeclared);
+        }
+        self.add_local(name.lexeme.clone());
+        None
+    }
+
+    fn add_local(&mut self, name: Cow<'a, str>) {
+        if self.function().locals.len() >= MAX_LOCALS {
+            self.limit(SyntaxKind::TooManyLocals);
+            return;
+        }
+        self.function().locals.push(Local {
+            name,
+            depth: None,
+            captured: false,
+        });
+    }
+
+    fn mark_initialized(&mut self) {
+        let state = self.function();
+        let depth = state.scope_depth;
+        if let Some(local) = state.locals.last_mut().filter(|_| depth > 0) {
+            local.depth = Some(depth);
+        }
+    }
+
+    fn define(&mut self, global: Option<u16>) {
+        match global {
+            Some(name) => self.emit_with(OpCode::DefineGlobal, &name.to_be_bytes(), self.line()),
+            None => self.mark_initialized(),
+        }
+    }
+
+    fn resolve(&mut self, name: &str) -> Slot {
+        let level = self.functions.len() - 1;
+        if let Some(slot) = self.resolve_local(level, name) {
+            return Slot::Local(slot);
+        }
+        if let Some(index) = self.resolve_upvalue(level, name) {
+            return Slot::Upvalue(index);
+        }
+        Slot::Global(self.identifier(name))
+    }
+
+    fn resolve_local(&self, level: usize, name: &str) -> Option<u8> {
+        let slot = self.functions[level]
+            .locals
+            .iter()
+            .rposition(|local| local.name == name)?;
+        u8::try_from(slot).ok()
+    }
+
+    fn resolve_upvalue(&mut self, level: usize, name: &str) -> Option<u8> {
+        if level == 0 {
+            return None;
+        }
+        if let Some(slot) = self.resolve_local(level - 1, name) {
+            self.functions[level - 1].locals[usize::from(slot)].captured = true;
+            return Some(self.add_upvalue(level, slot, true));
+        }
+        let index = self.resolve_upvalue(level - 1, name)?;
+        Some(self.add_upvalue(level, index, false))
+    }
+
+    fn add_upvalue(&mut self, level: usize, index: u8, is_local: bool) -> u8 {
+        let upvalue = Upvalue { index, is_local };
+        let upvalues = &mut self.functions[level].upvalues;
+        if let Some(i) = upvalues.iter().position(|&x| x == upvalue) {
+            return u8::try_from(i).expect("at most 256 upvalues");
+        }
+        if upvalues.len() >= MAX_LOCALS {
+            self.limit(SyntaxKind::TooManyUpvalues);
+            return 0;
+        }
+        upvalues.push(upvalue);
+        u8::try_from(upvalues.len() - 1).expect("at most 256 upvalues")
+    }
+
+    fn emit_variable(&mut self, slot: Slot, set: bool, line: usize) {
+        match slot {
+            Slot::Local(x) if set => self.emit_with(OpCode::SetLocal, &[x], line),
+            Slot::Local(x) => self.emit_with(OpCode::GetLocal, &[x], line),
+            Slot::Upvalue(x) if set => self.emit_with(OpCode::SetUpvalue, &[x], line),
+            Slot::Upvalue(x) => self.emit_with(OpCode::GetUpvalue, &[x], line),
+            Slot::Global(x) if set => self.emit_with(OpCode::SetGlobal, &x.to_be_bytes(), line),
+            Slot::Global(x) => self.emit_with(OpCode::GetGlobal, &x.to_be_bytes(), line),
+        }
+    }
+
+    /// Reads a variable.
+    fn variable(&mut self, name: Cow<'a, str>, line: usize) -> Target<'a> {
+        let errors = self.semantic.len();
+        let uninitialized = self
+            .function()
+            .locals
+            .iter()
+            .rev()
+            .find(|local| local.name == name)
+            .is_some_and(|local| local.depth.is_none());
+        if uninitialized {
+            self.semantic(line, &name, SemanticKind::ReadInOwnInitializer);
+        }
+        let slot = self.resolve(&name);
+        let start = self.chunk().code.len();
+        self.emit_variable(slot, false, line);
+        Target::Variable {
+            start,
+            name,
+            line,
+            errors,
+        }
+    }
+
+    fn declaration(&mut self) -> Result<(), Error> {
+        if self.next_if(&[Type::Var]).is_some() {
+            return self.var_declaration();
+        }
+        if self.next_if(&[Type::Fun]).is_some() {
+            let name = self.expect(
+                Type::Identifier,
+                SyntaxKind::ExpectFunctionName(FunctionKind::Function),
+            )?;
+            let global = self.declare(&name);
+            self.mark_initialized();
+            self.function_body(FunctionType::Function, &name)?;
+            self.define(global);
+            return Ok(());
+        }
+        if self.next_if(&[Type::Class]).is_some() {
+            return self.class_declaration();
+        }
+        self.statement()
+    }
+
+    fn class_declaration(&mut self) -> Result<(), Error> {
+        let name = self.expect(Type::Identifier, SyntaxKind::ExpectClassName)?;
+        let line = name.span.line;
+        let constant = self.identifier(&name.lexeme);
+        let global = self.declare(&name);
+        self.emit_with(OpCode::Class, &constant.to_be_bytes(), line);
+        self.define(global);
+        self.classes.push(ClassState {
+            has_superclass: false,
+        });
+
+        if self.next_if(&[Type::Less]).is_some() {
+            let superclass = self.expect(Type::Identifier, SyntaxKind::ExpectSuperclassName)?;
+            let super_line = superclass.span.line;
+            if superclass.lexeme == name.lexeme {
+                self.semantic(
+                    super_line,
+                    &superclass.lexeme,
+                    SemanticKind::InheritFromSelf,
+                );
+            }
+            self.variable(superclass.lexeme, super_line);
+            self.begin_scope();
+            self.add_local(Cow::Borrowed("super"));
+            self.mark_initialized();
+            self.variable(name.lexeme.clone(), line);
+            self.emit(OpCode::Inherit, super_line);
+            if let Some(class) = self.classes.last_mut() {
+                class.has_superclass = true;
+            }
+        }
+
+        self.variable(name.lexeme.clone(), line);
+        self.expect(Type::LeftBrace, SyntaxKind::ExpectLeftBraceBeforeClassBody)?;
+        while !matches!(self.current.ty, Type::RightBrace | Type::Eof) {
+            let method = self.expect(
+                Type::Identifier,
+                SyntaxKind::ExpectFunctionName(FunctionKind::Method),
+            )?;
+            let constant = self.identifier(&method.lexeme);
+            let ty = if method.lexeme == "init" {
+                FunctionType::Initializer
+            } else {
+                FunctionType::Method
+            };
+            self.function_body(ty, &method)?;
+            self.emit_with(OpCode::Method, &constant.to_be_bytes(), method.span.line);
+        }
+        self.expect(Type::RightBrace, SyntaxKind::ExpectRightBraceAfterClassBody)?;
+        self.emit(OpCode::Pop, self.line());
+
+        if self.classes.pop().is_some_and(|x| x.has_superclass) {
+            self.end_scope();
+        }
+        Ok(())
+    }
+
+    /// Compiles the rest of a function whose name was already consumed, leaving a closure of it
+    /// on the stack.
+    fn function_body(&mut self, ty: FunctionType, name: &Token<'a>) -> Result<(), Error> {
+        let kind = match ty {
+            FunctionType::Function => FunctionKind::Function,
+            _ => FunctionKind::Method,
+        };
+        let name = self.intern(&name.lexeme);
+        self.functions.push(State::new(ty, Some(name)));
+        self.begin_scope();
+
+        self.expect(
+            Type::LeftParen,
+            SyntaxKind::ExpectLeftParenAfterFunctionName(kind),
+        )?;
+        if self.current.ty != Type::RightParen {
+            loop {
+                if self.function().arity >= MAX_ARGS {
+                    self.syntax.push(self.error(SyntaxKind::TooManyParameters));
+                }
+                let param = self.expect(Type::Identifier, SyntaxKind::ExpectParameterName)?;
+                self.function().arity += 1;
+                self.declare(&param);
+                self.mark_initialized();
+                if self.next_if(&[Type::Comma]).is_none() {
+                    break;
+                }
+            }
+        }
+        self.expect(
+            Type::RightParen,
+            SyntaxKind::ExpectRightParenAfterParameters,
+        )?;
+        self.expect(Type::LeftBrace, SyntaxKind::ExpectLeftBraceBeforeBody(kind))?;
+        self.block()?;
+
+        let (function, upvalues) = self.end_function();
+        let line = self.line();
+        let constant = self.make_constant(Value::from(function));
+        let mut operands = constant.to_be_bytes().to_vec();
+        for upvalue in upvalues {
+            operands.extend([u8::from(upvalue.is_local), upvalue.index]);
+        }
+        self.emit_with(OpCode::Closure, &operands, line);
+        Ok(())
+    }
+
+    fn var_declaration(&mut self) -> Result<(), Error> {
+        let name = self.expect(Type::Identifier, SyntaxKind::ExpectVariableName)?;
+        let global = self.declare(&name);
+        if self.next_if(&[Type::Equal]).is_some() {
+            self.expression()?;
+        } else {
+            self.emit(OpCode::Nil, name.span.line);
+        }
+        self.expect(Type::Semicolon, SyntaxKind::ExpectSemicolonAfterVariable)?;
+        self.define(global);
+        Ok(())
+    }
+
+    fn statement(&mut self) -> Result<(), Error> {
+        let Some(token) = self.next_if(&[
+            Type::Print,
+            Type::LeftBrace,
+            Type::If,
+            Type::While,
+            Type::For,
+            Type::Return,
+        ]) else {
+            self.expression()?;
+            self.expect(Type::Semicolon, SyntaxKind::ExpectSemicolonAfterExpression)?;
+            self.emit(OpCode::Pop, self.line());
+            return Ok(());
+        };
+
+        match token.ty {
+            Type::Print => {
+                self.expression()?;
+                self.expect(Type::Semicolon, SyntaxKind::ExpectSemicolonAfterValue)?;
+                self.emit(OpCode::Print, self.line());
+            }
+            Type::LeftBrace => {
+                self.begin_scope();
+                self.block()?;
+                self.end_scope();
+            }
+            Type::If => self.if_statement()?,
+            Type::While => self.while_statement()?,
+            Type::For => self.for_statement()?,
+            Type::Return => self.return_statement(token.span.line)?,
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
+
+    /// Compiles the rest of a block whose `{` was already consumed.
+    fn block(&mut self) -> Result<(), Error> {
+        while !matches!(self.current.ty, Type::RightBrace | Type::Eof) {
+            self.declaration()?;
+        }
+        self.expect(Type::RightBrace, SyntaxKind::ExpectRightBrace)?;
+        Ok(())
+    }
+
+    fn if_statement(&mut self) -> Result<(), Error> {
+        self.expect(Type::LeftParen, SyntaxKind::ExpectLeftParenAfterIf)?;
+        self.expression()?;
+        self.expect(Type::RightParen, SyntaxKind::ExpectRightParenAfterIf)?;
+
+        let line = self.line();
+        let then_jump = self.emit_jump(OpCode::JumpIfFalse, line);
+        self.emit(OpCode::Pop, line);
+        self.statement()?;
+        let else_jump = self.emit_jump(OpCode::Jump, self.line());
+        self.patch_jump(then_jump);
+        self.emit(OpCode::Pop, line);
+        if self.next_if(&[Type::Else]).is_some() {
+            self.statement()?;
+        }
+        self.patch_jump(else_jump);
+        Ok(())
+    }
+
+    fn while_statement(&mut self) -> Result<(), Error> {
+        let start = self.chunk().code.len();
+        self.expect(Type::LeftParen, SyntaxKind::ExpectLeftParenAfterWhile)?;
+        self.expression()?;
+        self.expect(Type::RightParen, SyntaxKind::ExpectRightParenAfterWhile)?;
+
+        let line = self.line();
+        let exit_jump = self.emit_jump(OpCode::JumpIfFalse, line);
+        self.emit(OpCode::Pop, line);
+        self.statement()?;
+        self.emit_loop(start, self.line());
+        self.patch_jump(exit_jump);
+        self.emit(OpCode::Pop, line);
+        Ok(())
+    }
+
+    /// Compiles a `for` loop, with the increment jumped over on the way into the body.
+    fn for_statement(&mut self) -> Result<(), Error> {
+        self.begin_scope();
+        self.expect(Type::LeftParen, SyntaxKind::ExpectLeftParenAfterFor)?;
+
+        match self.current.ty {
+            Type::Semicolon => {
+                self.advance();
+            }
+            Type::Var => {
+                self.advance();
+                self.var_declaration()?;
+            }
+            _ => {
+                self.expression()?;
+                self.expect(Type::Semicolon, SyntaxKind::ExpectSemicolonAfterExpression)?;
+                self.emit(OpCode::Pop, self.line());
+            }
+        }
+
+        let mut start = self.chunk().code.len();
+        let condition = self.current.ty != Type::Semicolon;
+        if condition {
+            self.expression()?;
+        }
+        self.expect(
+            Type::Semicolon,
+            SyntaxKind::ExpectSemicolonAfterLoopCondition,
+        )?;
+        let line = self.line();
+        let exit_jump = condition.then(|| {
+            let jump = self.emit_jump(OpCode::JumpIfFalse, line);
+            self.emit(OpCode::Pop, line);
+            jump
+        });
+
+        // The resolver goes through the increment after the body.
+        let increment = if self.current.ty == Type::RightParen {
+            None
+        } else {
+            let body_jump = self.emit_jump(OpCode::Jump, self.line());
+            let increment_start = self.chunk().code.len();
+            let errors = self.semantic.len();
+            self.expression()?;
+            self.emit(OpCode::Pop, self.line());
+            self.emit_loop(start, self.line());
+            start = increment_start;
+            self.patch_jump(body_jump);
+            Some((errors, self.semantic.len() - errors))
+        };
+        self.expect(Type::RightParen, SyntaxKind::ExpectRightParenAfterFor)?;
+
+        self.statement()?;
+        if let Some((errors, count)) = increment {
+            self.semantic[errors..].rotate_left(count);
+        }
+        self.emit_loop(start, self.line());
+        if let Some(exit_jump) = exit_jump {
+            self.patch_jump(exit_jump);
+            self.emit(OpCode::Pop, line);
+        }
+        self.end_scope();
+        Ok(())
+    }
+
+    /// Compiles the rest of a `return` statement on `line`.
+    fn return_statement(&mut self, line: usize) -> Result<(), Error> {
+        let ty = self.function().ty;
+        if ty == FunctionType::Script {
+            self.semantic(line, "return", SemanticKind::TopLevelReturn);
+        }
+        if self.current.ty == Type::Semicolon {
+            self.advance();
+            self.emit_return(line);
+            return Ok(());
+        }
+
+        if ty == FunctionType::Initializer {
+            self.semantic(line, "return", SemanticKind::ReturnFromInitializer);
+        }
+        self.expression()?;
+        self.expect(Type::Semicolon, SyntaxKind::ExpectSemicolonAfterReturn)?;
+        self.emit(OpCode::Return, line);
+        Ok(())
+    }
+
+    fn expression(&mut self) -> Result<(), Error> {
+        self.assignment()
+    }
+
+    fn assignment(&mut self) -> Result<(), Error> {
+        let target = self.or()?;
+        let Some(equals) = self.next_if(&[Type::Equal]) else {
+            return Ok(());
+        };
+
+        match target {
+            Target::Variable {
+                start,
+                name,
+                line,
+                errors,
+            } => {
+                // Assigning isn't reading, in its own initializer or not.
+                self.chunk().truncate(start);
+                self.semantic.truncate(errors);
+                self.assignment()?;
+                let slot = self.resolve(&name);
+                self.emit_variable(slot, true, line);
+            }
+            Target::Property {
+                start,
+                name,
+                line,
+                errors,
+            } => {
+                self.chunk().truncate(start);
+                let object = self.semantic.len() - errors;
+                self.assignment()?;
+                // The resolver goes through the value before the object.
+                self.semantic[errors..].rotate_left(object);
+                self.emit_with(OpCode::SetProperty, &name.to_be_bytes(), line);
+            }
+            Target::None => {
+                self.assignment()?;
+                // Reported without unwinding, the parser is not in a confused state.
+                self.syntax
+                    .push(Error::syntax(&equals, SyntaxKind::InvalidAssignmentTarget));
+            }
+        }
+        Ok(())
+    }
+
+    fn logical(
+        &mut self,
+        ty: Type,
+        operand: fn(&mut Self) -> Result<Target<'a>, Error>,
+    ) -> Result<Target<'a>, Error> {
+        let mut target = operand(self)?;
+        while let Some(op) = self.next_if(&[ty]) {
+            let line = op.span.line;
+            let end_jump = if ty == Type::Or {
+                let else_jump = self.emit_jump(OpCode::JumpIfFalse, line);
+                let end_jump = self.emit_jump(OpCode::Jump, line);
+                self.patch_jump(else_jump);
+                end_jump
+            } else {
+                self.emit_jump(OpCode::JumpIfFalse, line)
+            };
+            self.emit(OpCode::Pop, line);
+            operand(self)?;
+            self.patch_jump(end_jump);
+            target = Target::None;
+        }
+        Ok(target)
+    }
+
+    fn or(&mut self) -> Result<Target<'a>, Error> {
+        self.logical(Type::Or, Self::and)
+    }
+
+    fn and(&mut self) -> Result<Target<'a>, Error> {
+        self.logical(Type::And, Self::equality)
+    }
+
+    fn binary(
+        &mut self,
+        types: &[Type],
+        operand: fn(&mut Self) -> Result<Target<'a>, Error>,
+    ) -> Result<Target<'a>, Error> {
+        let mut target = operand(self)?;
+        while let Some(op) = self.next_if(types) {
+            operand(self)?;
+            let line = op.span.line;
+            let op = match op.ty {
+                Type::BangEqual => {
+                    self.emit(OpCode::Equal, line);
+                    OpCode::Not
+                }
+                Type::EqualEqual => OpCode::Equal,
+                Type::Greater => OpCode::Greater,
+                Type::GreaterEqual => OpCode::GreaterEqual,
+                Type::Less => OpCode::Less,
+                Type::LessEqual => OpCode::LessEqual,
+                Type::Minus => OpCode::Subtract,
+                Type::Plus => OpCode::Add,
+                Type::Slash => OpCode::Divide,
+                Type::Star => OpCode::Multiply,
+                _ => unreachable!("not a binary operator: {op}"),
+            };
+            self.emit(op, line);
+            target = Target::None;
+        }
+        Ok(target)
+    }
+
+    fn equality(&mut self) -> Result<Target<'a>, Error> {
+        self.binary(&[Type::BangEqual, Type::EqualEqual], Self::comparison)
+    }
+
+    fn comparison(&mut self) -> Result<Target<'a>, Error> {
+        self.binary(
+            &[
+                Type::Greater,
+                Type::GreaterEqual,
+                Type::Less,
+                Type::LessEqual,
+            ],
+            Self::term,
+        )
+    }
+
+    fn term(&mut self) -> Result<Target<'a>, Error> {
+        self.binary(&[Type::Minus, Type::Plus], Self::factor)
+    }
+
+    fn factor(&mut self) -> Result<Target<'a>, Error> {
+        self.binary(&[Type::Slash, Type::Star], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Target<'a>, Error> {
+        if let Some(op) = self.next_if(&[Type::Bang, Type::Minus]) {
+            self.unary()?;
+            let code = match op.ty {
+                Type::Bang => OpCode::Not,
+                _ => OpCode::Negate,
+            };
+            self.emit(code, op.span.line);
+            return Ok(Target::None);
+        }
+        self.call()
+    }
+
+    fn call(&mut self) -> Result<Target<'a>, Error> {
+        let errors = self.semantic.len();
+        let mut target = self.primary()?;
+        while let Some(token) = self.next_if(&[Type::LeftParen, Type::Dot]) {
+            if token.ty == Type::Dot {
+                let name = self.expect(Type::Identifier, SyntaxKind::ExpectPropertyName)?;
+                let line = name.span.line;
+                let name = self.identifier(&name.lexeme);
+                let start = self.chunk().code.len();
+                self.emit_with(OpCode::GetProperty, &name.to_be_bytes(), line);
+                target = Target::Property {
+                    start,
+                    name,
+                    line,
+                    errors,
+                };
+                continue;
+            }
+
+            let mut count = 0;
+            if self.current.ty != Type::RightParen {
+                loop {
+                    if count >= MAX_ARGS {
+                        self.syntax.push(self.error(SyntaxKind::TooManyArguments));
+                    }
+                    self.expression()?;
+                    count += 1;
+                    if self.next_if(&[Type::Comma]).is_none() {
+                        break;
+                    }
+                }
+            }
+            let paren =
+                self.expect(Type::RightParen, SyntaxKind::ExpectRightParenAfterArguments)?;
+            let count = u8::try_from(count).unwrap_or(u8::MAX);
+            self.emit_with(OpCode::Call, &[count], paren.span.line);
+            target = Target::None;
+        }
+        Ok(target)
+    }
+
+    fn primary(&mut self) -> Result<Target<'a>, Error> {
+        let line = self.current.span.line;
+        match self.current.ty {
+            Type::False => self.emit(OpCode::False, line),
+            Type::True => self.emit(OpCode::True, line),
+            Type::Nil => self.emit(OpCode::Nil, line),
+            Type::Number | Type::String => {
+                let value = match self.current.literal.clone() {
+                    Literal::Number(x) => Value::from(x),
+                    Literal::String(x) => Value::from(self.intern(&x)),
+                    Literal::Null => unreachable!("literal without a value"),
+                };
+                self.constant(value, line);
+            }
+            _ => return self.named(),
+        }
+        self.advance();
+        Ok(Target::None)
+    }
+
+    /// Compiles the primary expressions that aren't literals.
+    fn named(&mut self) -> Result<Target<'a>, Error> {
+        let line = self.current.span.line;
+        match self.current.ty {
+            Type::Identifier => {
+                let name = self.advance();
+                Ok(self.variable(name.lexeme, line))
+            }
+            Type::This => {
+                self.advance();
+                if self.classes.is_empty() {
+                    self.semantic(line, "this", SemanticKind::ThisOutsideClass);
+                } else {
+                    self.variable(Cow::Borrowed("this"), line);
+                }
+                Ok(Target::None)
+            }
+            Type::Super => {
+                self.advance();
+                self.expect(Type::Dot, SyntaxKind::ExpectDotAfterSuper)?;
+                let method =
+                    self.expect(Type::Identifier, SyntaxKind::ExpectSuperclassMethodName)?;
+                match self.classes.last() {
+                    None => self.semantic(line, "super", SemanticKind::SuperOutsideClass),
+                    Some(class) if !class.has_superclass => {
+                        self.semantic(line, "super", SemanticKind::SuperWithoutSuperclass);
+                    }
+                    Some(_) => {
+                        let name = self.identifier(&method.lexeme);
+                        self.variable(Cow::Borrowed("this"), line);
+                        self.variable(Cow::Borrowed("super"), line);
+                        self.emit_with(OpCode::GetSuper, &name.to_be_bytes(), line);
+                    }
+                }
+                Ok(Target::None)
+            }
+            Type::LeftParen => {
+                self.advance();
+                self.expression()?;
+                self.expect(Type::RightParen, SyntaxKind::ExpectRightParen)?;
+                Ok(Target::None)
+            }
+            _ => Err(self.error(SyntaxKind::ExpectExpression)),
+        }
+    }
+}
+
+/// Marks the roots of the virtual machine and the objects of the functions being compiled.
+fn mark_roots(heap: &mut Heap, roots: &[Value], functions: &[State]) {
+    for &value in roots {
+        heap.mark(value);
+    }
+    for state in functions {
+        if let Some(name) = state.name {
+            heap.mark(Value::from(name));
+        }
+        for &constant in &state.chunk.constants {
+            heap.mark(constant);
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::io::{self, Write};
+
+use super::{heap::Obj, Chunk, Heap, ObjRef, OpCode, Value};
+
+/// Writes the constants and code of `function` and then of every function it defines, in the
+/// style of clox.
+pub fn disassemble(heap: &Heap, function: ObjRef, out: &mut impl Write) -> io::Result<()> {
+    let mut pending = vec![function];
+    while let Some(function) = pending.pop() {
+        writeln!(out, "== {} ==", heap.display(Value::from(function)))?;
+        let chunk = &heap.function(function).chunk;
+        constants(heap, chunk, out)?;
+        let mut offset = 0;
+        while offset < chunk.code.len() {
+            offset = instruction(heap, chunk, offset, out)?;
+        }
+
+        let nested = chunk
+            .constants
+            .iter()
+            .filter_map(|x| x.as_obj())
+            .filter(|&x| matches!(heap.get(x), Obj::Function(_)));
+        let len = pending.len();
+        pending.extend(nested);
+        pending[len..].reverse();
+    }
+    Ok(())
+}
+
+/// Writes the index and value of every constant of `chunk`, quoted as instructions quote them.
+fn constants(heap: &Heap, chunk: &Chunk, out: &mut impl Write) -> io::Result<()> {
+    if chunk.constants.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "constants:")?;
+    for (index, &value) in chunk.constants.iter().enumerate() {
+        let function = value
+            .as_obj()
+            .is_some_and(|x| matches!(heap.get(x), Obj::Function(_)));
+        if function {
+            writeln!(out, "{index:4} {}", heap.display(value))?;
+        } else {
+            writeln!(out, "{index:4} '{}'", heap.display(value))?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the instruction at `offset`, returning the offset of the next one.
+fn instruction(
+    heap: &Heap,
+    chunk: &Chunk,
+    offset: usize,
+    out: &mut impl Write,
+) -> io::Result<usize> {
+    write!(out, "{offset:04} ")?;
+    let line = chunk.line(offset);
+    if offset > 0 && line == chunk.line(offset - 1) {
+        write!(out, "   | ")?;
+    } else {
+        write!(out, "{line:4} ")?;
+    }
+
+    let byte = chunk.code[offset];
+    let Some(op) = OpCode::from_repr(byte) else {
+        writeln!(out, "Unknown opcode {byte}")?;
+        return Ok(offset + 1);
+    };
+    let name = format!("OP_{op}");
+    let short = |at: usize| usize::from(u16::from_be_bytes([chunk.code[at], chunk.code[at + 1]]));
+    match op {
+        OpCode::Constant
+        | OpCode::GetGlobal
+        | OpCode::DefineGlobal
+        | OpCode::SetGlobal
+        | OpCode::GetProperty
+        | OpCode::SetProperty
+        | OpCode::GetSuper
+        | OpCode::Class
+        | OpCode::Method => {
+            let constant = short(offset + 1);
+            let value = heap.display(chunk.constants[constant]);
+            writeln!(out, "{name:<16} {constant:4} '{value}'")?;
+            Ok(offset + 3)
+        }
+        OpCode::GetLocal
+        | OpCode::SetLocal
+        | OpCode::GetUpvalue
+        | OpCode::SetUpvalue
+        | OpCode::Call => {
+            let operand = chunk.code[offset + 1];
+            writeln!(out, "{name:<16} {operand:4}")?;
+            Ok(offset + 2)
+        }
+        OpCode::Jump | OpCode::JumpIfFalse | OpCode::Loop => {
+            let jump = short(offset + 1);
+            let target = if op == OpCode::Loop {
+                offset + 3 - jump
+            } else {
+                offset + 3 + jump
+            };
+            writeln!(out, "{name:<16} {offset:4} -> {target}")?;
+            Ok(offset + 3)
+        }
+        OpCode::Closure => {
+            let constant = short(offset + 1);
+            let function = chunk.constants[constant];
+            writeln!(out, "{name:<16} {constant:4} {}", heap.display(function))?;
+            let function = function.as_obj().expect("closures are of functions");
+            let mut offset = offset + 3;
+            for _ in 0..heap.function(function).upvalues {
+                let kind = if chunk.code[offset] == 0 {
+                    "upvalue"
+                } else {
+                    "local"
+                };
+                let index = chunk.code[offset + 1];
+                writeln!(out, "{offset:04}    |                     {kind} {index}")?;
+                offset += 2;
+            }
+            Ok(offset)
+        }
+        _ => {
+            writeln!(out, "{name}")?;
+            Ok(offset + 1)
+        }
+    }
+}
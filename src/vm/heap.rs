@@ -0,0 +1,484 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::{Chunk, Value};
+
+/// Handle to an object on the [`Heap`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ObjRef(u32);
+
+impl ObjRef {
+    #[inline]
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[cfg(feature = "nan-boxing")]
+impl ObjRef {
+    #[inline]
+    pub const fn from_raw(raw: u32) -> Self {
+        Self(raw)
+    }
+
+    #[inline]
+    pub const fn to_raw(self) -> u32 {
+        self.0
+    }
+}
+
+#[derive(Debug)]
+pub enum Obj {
+    String(Rc<str>),
+    Function(Function),
+    Native(Native),
+    Closure(Closure),
+    Upvalue(Upvalue),
+    Class(Class),
+    Instance(Instance),
+    BoundMethod(BoundMethod),
+}
+
+impl Obj {
+    /// Approximate size in bytes, counting what the object owns at allocation.
+    fn size(&self) -> usize {
+        let owned = match self {
+            Self::String(x) => x.len(),
+            Self::Function(x) => {
+                x.chunk.code.len() + x.chunk.constants.len() * std::mem::size_of::<Value>()
+            }
+            Self::Closure(x) => x.upvalues.len() * std::mem::size_of::<ObjRef>(),
+            Self::Class(x) => x.methods.len() * std::mem::size_of::<(ObjRef, ObjRef)>(),
+            Self::Instance(x) => x.fields.len() * std::mem::size_of::<(ObjRef, Value)>(),
+            Self::Native(_) | Self::Upvalue(_) | Self::BoundMethod(_) => 0,
+        };
+        std::mem::size_of::<Self>() + owned
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Function {
+    pub arity: usize,
+    pub upvalues: usize,
+    pub chunk: Rc<Chunk>,
+    /// `None` for the top-level script.
+    pub name: Option<ObjRef>,
+}
+
+/// A function implemented by the virtual machine itself.
+#[derive(Debug, Clone, Copy)]
+pub struct Native {
+    pub name: &'static str,
+    pub arity: usize,
+    pub fun: fn(&[Value]) -> Value,
+}
+
+impl Native {
+    pub const CLOCK: Self = Self {
+        name: "clock",
+        arity: 0,
+        fun: |_| {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            Value::from(now.as_secs_f64())
+        },
+    };
+
+    pub const ALL: [Self; 1] = [Self::CLOCK];
+}
+
+#[derive(Debug)]
+pub struct Closure {
+    pub function: ObjRef,
+    pub upvalues: Vec<ObjRef>,
+}
+
+/// A variable captured by a closure.
+#[derive(Debug, Clone, Copy)]
+pub enum Upvalue {
+    /// Still on the stack, in this slot.
+    Open(usize),
+    /// Moved off the stack when its scope ended.
+    Closed(Value),
+}
+
+#[derive(Debug)]
+pub struct Class {
+    pub name: ObjRef,
+    /// Closures by name.
+    pub methods: HashMap<ObjRef, ObjRef>,
+}
+
+#[derive(Debug)]
+pub struct Instance {
+    pub class: ObjRef,
+    pub fields: HashMap<ObjRef, Value>,
+}
+
+/// A method along with the instance it was accessed on.
+#[derive(Debug)]
+pub struct BoundMethod {
+    pub receiver: Value,
+    pub method: ObjRef,
+}
+
+/// Bytes allocated before the first collection.
+const INITIAL_THRESHOLD: usize = 1 << 20;
+
+/// How much the heap may grow after a collection before the next one.
+const GROWTH_FACTOR: usize = 2;
+
+/// Objects of the virtual machine, with strings interned so that they compare by handle.
+///
+/// Memory is reclaimed by [`Heap::collect`], which frees every object not reachable from those
+/// marked beforehand. The slots of freed objects are reused by later allocations.
+#[derive(Debug)]
+pub struct Heap {
+    objects: Vec<Option<Obj>>,
+    /// Approximate size of each object, in bytes.
+    sizes: Vec<usize>,
+    free: Vec<ObjRef>,
+    /// Weak: entries go away along with their string.
+    strings: HashMap<Rc<str>, ObjRef>,
+    marker: Marker,
+    allocated: usize,
+    threshold: usize,
+    /// Whether to collect before every allocation.
+    stress: bool,
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self {
+            objects: Vec::new(),
+            sizes: Vec::new(),
+            free: Vec::new(),
+            strings: HashMap::new(),
+            marker: Marker::default(),
+            allocated: 0,
+            threshold: INITIAL_THRESHOLD,
+            stress: false,
+        }
+    }
+}
+
+impl Heap {
+    /// Collects garbage before every allocation, to flush out objects left unmarked.
+    pub fn set_stress(&mut self, stress: bool) {
+        self.stress = stress;
+    }
+
+    /// Whether the next allocation should be preceded by a collection.
+    const fn should_collect(&self) -> bool {
+        self.stress || self.allocated > self.threshold
+    }
+
+    /// Allocates `obj`, first collecting garbage if the heap has grown enough. `mark_roots` marks
+    /// what must survive that collection, besides what `obj` refers to.
+    pub fn alloc_with(&mut self, obj: Obj, mark_roots: impl FnOnce(&mut Self)) -> ObjRef {
+        if self.should_collect() {
+            self.collect_with(|heap| {
+                mark_roots(heap);
+                heap.marker.children(&obj);
+            });
+        }
+        self.alloc(obj)
+    }
+
+    /// Like [`Heap::intern`], first collecting garbage as [`Heap::alloc_with`] does if a string
+    /// gets allocated.
+    pub fn intern_with(&mut self, string: &str, mark_roots: impl FnOnce(&mut Self)) -> ObjRef {
+        if let Some(obj) = self.find(string) {
+            return obj;
+        }
+        if self.should_collect() {
+            self.collect_with(mark_roots);
+        }
+        self.intern(string)
+    }
+
+    /// Allocates `obj` without collecting garbage, for objects created before there are any
+    /// roots.
+    pub fn alloc(&mut self, obj: Obj) -> ObjRef {
+        let size = obj.size();
+        self.allocated += size;
+        if let Some(index) = self.free.pop() {
+            self.objects[index.index()] = Some(obj);
+            self.sizes[index.index()] = size;
+            return index;
+        }
+        let index = u32::try_from(self.objects.len()).expect("fewer than 2^32 objects");
+        self.objects.push(Some(obj));
+        self.sizes.push(size);
+        self.marker.marked.push(false);
+        ObjRef(index)
+    }
+
+    /// The string object for `string`, if there is one already.
+    pub fn find(&self, string: &str) -> Option<ObjRef> {
+        self.strings.get(string).copied()
+    }
+
+    /// The string object for `string`, allocating it only if there is none yet.
+    pub fn intern(&mut self, string: &str) -> ObjRef {
+        if let Some(obj) = self.find(string) {
+            return obj;
+        }
+        let string = Rc::<str>::from(string);
+        let obj = self.alloc(Obj::String(Rc::clone(&string)));
+        self.strings.insert(string, obj);
+        obj
+    }
+
+    /// Keeps `value` alive through the next collection.
+    pub fn mark(&mut self, value: Value) {
+        self.marker.value(value);
+    }
+
+    /// Frees every object not reachable from those `mark_roots` marks, then sets the size the
+    /// heap may grow to before collecting again.
+    pub fn collect_with(&mut self, mark_roots: impl FnOnce(&mut Self)) {
+        mark_roots(self);
+        while let Some(obj) = self.marker.gray.pop() {
+            if let Some(obj) = &self.objects[obj.index()] {
+                self.marker.children(obj);
+            }
+        }
+
+        let marked = &mut self.marker.marked;
+        self.strings.retain(|_, obj| marked[obj.index()]);
+        for (index, slot) in self.objects.iter_mut().enumerate() {
+            if std::mem::take(&mut marked[index]) {
+                debug_assert_eq!(
+                    slot.as_ref().map(Obj::size),
+                    Some(self.sizes[index]),
+                    "growth of object {index} went uncounted"
+                );
+                continue;
+            }
+            if slot.is_none() {
+                continue;
+            }
+            *slot = None;
+            self.allocated -= self.sizes[index];
+            self.free.push(ObjRef(
+                u32::try_from(index).expect("fewer than 2^32 objects"),
+            ));
+        }
+        self.threshold = INITIAL_THRESHOLD.max(self.allocated * GROWTH_FACTOR);
+    }
+
+    #[cfg(test)]
+    pub fn live(&self) -> usize {
+        self.objects.len() - self.free.len()
+    }
+
+    #[inline]
+    pub fn get(&self, obj: ObjRef) -> &Obj {
+        self.objects[obj.index()].as_ref().expect("live object")
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, obj: ObjRef) -> &mut Obj {
+        self.objects[obj.index()].as_mut().expect("live object")
+    }
+
+    pub fn string(&self, obj: ObjRef) -> &Rc<str> {
+        match self.get(obj) {
+            Obj::String(x) => x,
+            x => unreachable!("not a string: {x:?}"),
+        }
+    }
+
+    pub fn function(&self, obj: ObjRef) -> &Function {
+        match self.get(obj) {
+            Obj::Function(x) => x,
+            x => unreachable!("not a function: {x:?}"),
+        }
+    }
+
+    pub fn closure(&self, obj: ObjRef) -> &Closure {
+        match self.get(obj) {
+            Obj::Closure(x) => x,
+            x => unreachable!("not a closure: {x:?}"),
+        }
+    }
+
+    pub fn upvalue(&self, obj: ObjRef) -> Upvalue {
+        match self.get(obj) {
+            Obj::Upvalue(x) => *x,
+            x => unreachable!("not an upvalue: {x:?}"),
+        }
+    }
+
+    pub fn upvalue_mut(&mut self, obj: ObjRef) -> &mut Upvalue {
+        match self.get_mut(obj) {
+            Obj::Upvalue(x) => x,
+            x => unreachable!("not an upvalue: {x:?}"),
+        }
+    }
+
+    /// Adds `methods` to `class`, replacing any of the same name.
+    pub fn add_methods(
+        &mut self,
+        class: ObjRef,
+        methods: impl IntoIterator<Item = (ObjRef, ObjRef)>,
+    ) {
+        let Obj::Class(x) = self.get_mut(class) else {
+            unreachable!("not a class: {class:?}");
+        };
+        let added = methods
+            .into_iter()
+            .filter(|&(name, method)| x.methods.insert(name, method).is_none())
+            .count();
+        self.grow(class, added * std::mem::size_of::<(ObjRef, ObjRef)>());
+    }
+
+    pub fn set_field(&mut self, instance: ObjRef, name: ObjRef, value: Value) {
+        let Obj::Instance(x) = self.get_mut(instance) else {
+            unreachable!("not an instance: {instance:?}");
+        };
+        if x.fields.insert(name, value).is_none() {
+            self.grow(instance, std::mem::size_of::<(ObjRef, Value)>());
+        }
+    }
+
+    /// Counts `bytes` more towards `obj`, which grew since it was allocated.
+    fn grow(&mut self, obj: ObjRef, bytes: usize) {
+        self.sizes[obj.index()] += bytes;
+        self.allocated += bytes;
+    }
+
+    /// The string object holding `value`, if it is one.
+    pub fn as_string(&self, value: Value) -> Option<&Rc<str>> {
+        match self.get(value.as_obj()?) {
+            Obj::String(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn as_class(&self, value: Value) -> Option<&Class> {
+        match self.get(value.as_obj()?) {
+            Obj::Class(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn as_instance(&self, value: Value) -> Option<&Instance> {
+        match self.get(value.as_obj()?) {
+            Obj::Instance(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// Formats `value` the way `print` shows it.
+    pub const fn display(&self, value: Value) -> Displayed<'_> {
+        Displayed { heap: self, value }
+    }
+}
+
+/// Marks of the objects reached during a collection.
+#[derive(Debug, Default)]
+struct Marker {
+    marked: Vec<bool>,
+    /// Marked objects whose references are yet to be marked.
+    gray: Vec<ObjRef>,
+}
+
+impl Marker {
+    fn obj(&mut self, obj: ObjRef) {
+        let marked = &mut self.marked[obj.index()];
+        if !*marked {
+            *marked = true;
+            self.gray.push(obj);
+        }
+    }
+
+    fn value(&mut self, value: Value) {
+        if let Some(obj) = value.as_obj() {
+            self.obj(obj);
+        }
+    }
+
+    fn children(&mut self, obj: &Obj) {
+        match obj {
+            Obj::String(_) | Obj::Native(_) | Obj::Upvalue(Upvalue::Open(_)) => {}
+            Obj::Function(x) => {
+                if let Some(name) = x.name {
+                    self.obj(name);
+                }
+                for &constant in &x.chunk.constants {
+                    self.value(constant);
+                }
+            }
+            Obj::Closure(x) => {
+                self.obj(x.function);
+                for &upvalue in &x.upvalues {
+                    self.obj(upvalue);
+                }
+            }
+            Obj::Upvalue(Upvalue::Closed(x)) => self.value(*x),
+            Obj::Class(x) => {
+                self.obj(x.name);
+                for (&name, &method) in &x.methods {
+                    self.obj(name);
+                    self.obj(method);
+                }
+            }
+            Obj::Instance(x) => {
+                self.obj(x.class);
+                for (&name, &value) in &x.fields {
+                    self.obj(name);
+                    self.value(value);
+                }
+            }
+            Obj::BoundMethod(x) => {
+                self.value(x.receiver);
+                self.obj(x.method);
+            }
+        }
+    }
+}
+
+pub struct Displayed<'h> {
+    heap: &'h Heap,
+    value: Value,
+}
+
+impl Display for Displayed<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(x) = self.value.as_number() {
+            return x.fmt(f);
+        }
+        if let Some(x) = self.value.as_bool() {
+            return x.fmt(f);
+        }
+        let Some(obj) = self.value.as_obj() else {
+            return write!(f, "nil");
+        };
+        let heap = self.heap;
+        match heap.get(obj) {
+            Obj::String(x) => x.fmt(f),
+            Obj::Function(x) => match x.name {
+                Some(name) => write!(f, "<fn {}>", heap.string(name)),
+                None => write!(f, "<script>"),
+            },
+            Obj::Native(_) => write!(f, "<native fn>"),
+            Obj::Closure(x) => heap.display(Value::from(x.function)).fmt(f),
+            Obj::Upvalue(_) => write!(f, "upvalue"),
+            Obj::Class(x) => heap.string(x.name).fmt(f),
+            Obj::Instance(x) => {
+                let Obj::Class(class) = heap.get(x.class) else {
+                    unreachable!("instance of a non-class");
+                };
+                write!(f, "{} instance", heap.string(class.name))
+            }
+            Obj::BoundMethod(x) => heap.display(Value::from(x.method)).fmt(f),
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use super::ObjRef;
+
+/// A value on the stack of the virtual machine, objects live on the [`Heap`](super::Heap).
+#[cfg(not(feature = "nan-boxing"))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    Obj(ObjRef),
+}
+
+#[cfg(not(feature = "nan-boxing"))]
+impl Value {
+    pub const NIL: Self = Self::Nil;
+
+    /// `false` and `nil` are falsey, everything else is truthy.
+    #[inline]
+    pub const fn is_falsey(self) -> bool {
+        matches!(self, Self::Nil | Self::Bool(false))
+    }
+
+    #[inline]
+    pub const fn as_number(self) -> Option<f64> {
+        match self {
+            Self::Number(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub const fn as_obj(self) -> Option<ObjRef> {
+        match self {
+            Self::Obj(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub const fn as_bool(self) -> Option<bool> {
+        match self {
+            Self::Bool(x) => Some(x),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(not(feature = "nan-boxing"))]
+impl From<f64> for Value {
+    #[inline]
+    fn from(x: f64) -> Self {
+        Self::Number(x)
+    }
+}
+
+#[cfg(not(feature = "nan-boxing"))]
+impl From<bool> for Value {
+    #[inline]
+    fn from(x: bool) -> Self {
+        Self::Bool(x)
+    }
+}
+
+#[cfg(not(feature = "nan-boxing"))]
+impl From<ObjRef> for Value {
+    #[inline]
+    fn from(x: ObjRef) -> Self {
+        Self::Obj(x)
+    }
+}
+
+/// A value on the stack of the virtual machine, objects live on the [`Heap`](super::Heap).
+///
+/// Packed into a single word: numbers are stored as they are, and everything else as a quiet
+/// NaN no arithmetic produces. Such NaNs with the sign bit set hold an object handle in their
+/// low bits, the others one of the tags of `nil`, `false` and `true`.
+#[cfg(feature = "nan-boxing")]
+#[derive(Clone, Copy)]
+pub struct Value(u64);
+
+#[cfg(feature = "nan-boxing")]
+impl Value {
+    /// Exponent bits, the quiet bit and one more, so that the NaNs of arithmetic stay numbers.
+    const QNAN: u64 = 0x7ffc_0000_0000_0000;
+    const SIGN: u64 = 1 << 63;
+
+    const TAG_NIL: u64 = 1;
+    const TAG_FALSE: u64 = 2;
+    const TAG_TRUE: u64 = 3;
+
+    pub const NIL: Self = Self(Self::QNAN | Self::TAG_NIL);
+    const FALSE: Self = Self(Self::QNAN | Self::TAG_FALSE);
+    const TRUE: Self = Self(Self::QNAN | Self::TAG_TRUE);
+
+    #[inline]
+    const fn is_number(self) -> bool {
+        self.0 & Self::QNAN != Self::QNAN
+    }
+
+    #[inline]
+    const fn is_obj(self) -> bool {
+        self.0 & (Self::QNAN | Self::SIGN) == Self::QNAN | Self::SIGN
+    }
+
+    /// `false` and `nil` are falsey, everything else is truthy.
+    #[inline]
+    pub const fn is_falsey(self) -> bool {
+        self.0 == Self::NIL.0 || self.0 == Self::FALSE.0
+    }
+
+    #[inline]
+    pub fn as_number(self) -> Option<f64> {
+        if self.is_number() {
+            Some(f64::from_bits(self.0))
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    pub const fn as_obj(self) -> Option<ObjRef> {
+        if self.is_obj() {
+            #[allow(clippy::cast_possible_truncation)]
+            let raw = self.0 as u32;
+            Some(ObjRef::from_raw(raw))
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    pub const fn as_bool(self) -> Option<bool> {
+        match self.0 {
+            x if x == Self::TRUE.0 => Some(true),
+            x if x == Self::FALSE.0 => Some(false),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "nan-boxing")]
+impl PartialEq for Value {
+    /// Numbers compare as numbers, so that `NaN` differs from itself, everything else by
+    /// identity.
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        match (self.as_number(), other.as_number()) {
+            (Some(l), Some(r)) => l == r,
+            _ => self.0 == other.0,
+        }
+    }
+}
+
+#[cfg(feature = "nan-boxing")]
+impl std::fmt::Debug for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(x) = self.as_number() {
+            f.debug_tuple("Number").field(&x).finish()
+        } else if let Some(x) = self.as_bool() {
+            f.debug_tuple("Bool").field(&x).finish()
+        } else if let Some(x) = self.as_obj() {
+            f.debug_tuple("Obj").field(&x).finish()
+        } else {
+            write!(f, "Nil")
+        }
+    }
+}
+
+#[cfg(feature = "nan-boxing")]
+impl From<f64> for Value {
+    #[inline]
+    fn from(x: f64) -> Self {
+        Self(x.to_bits())
+    }
+}
+
+#[cfg(feature = "nan-boxing")]
+impl From<bool> for Value {
+    #[inline]
+    fn from(x: bool) -> Self {
+        if x {
+            Self::TRUE
+        } else {
+            Self::FALSE
+        }
+    }
+}
+
+#[cfg(feature = "nan-boxing")]
+impl From<ObjRef> for Value {
+    #[inline]
+    fn from(x: ObjRef) -> Self {
+        Self(Self::SIGN | Self::QNAN | u64::from(x.to_raw()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    use crate::vm::Heap;
+
+    #[test]
+    fn round_trip() {
+        for x in [
+            0.0,
+            -0.0,
+            1.5,
+            -2.0,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::MAX,
+        ] {
+            assert_eq!(Value::from(x).as_number(), Some(x));
+        }
+        assert!(Value::from(f64::NAN).as_number().unwrap().is_nan());
+        assert!(Value::from(-f64::NAN).as_number().unwrap().is_nan());
+        assert_eq!(Value::from(true).as_bool(), Some(true));
+        assert_eq!(Value::from(false).as_bool(), Some(false));
+
+        let mut heap = Heap::default();
+        let obj = heap.intern("a");
+        assert_eq!(Value::from(obj).as_obj(), Some(obj));
+        assert_eq!(Value::from(obj).as_number(), None);
+        assert_eq!(Value::NIL.as_obj(), None);
+        assert_eq!(Value::NIL.as_bool(), None);
+        assert_eq!(Value::from(0.0).as_bool(), None);
+    }
+
+    #[test]
+    fn equality() {
+        assert_eq!(Value::from(0.0), Value::from(-0.0));
+        assert_ne!(Value::from(f64::NAN), Value::from(f64::NAN));
+        assert_ne!(Value::from(1.0), Value::from(true));
+        assert_ne!(Value::NIL, Value::from(false));
+        assert!(Value::NIL.is_falsey());
+        assert!(Value::from(false).is_falsey());
+        assert!(!Value::from(0.0).is_falsey());
+    }
+
+    #[test]
+    fn size() {
+        let words = if cfg!(feature = "nan-boxing") { 1 } else { 2 };
+        assert_eq!(std::mem::size_of::<Value>(), words * 8);
+    }
+}